@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::iter;
+use std::path::{Path, PathBuf};
+use crate::dataset::error::{column_after, open_file, DatasetError};
+use crate::dataset::tokenizer::Tokenizer;
+use crate::dataset::vocab::{is_user_file, save_lines};
+
+/// first line of merges file, the same as GPT-2 and HuggingFace tokenizers
+const MERGES_HEADER: &str = "#version: 0.2";
+
+/// merge pairs in rank order
+pub(crate) type Merges = Vec<(String, String)>;
+
+/// word of bpe trainer, symbols are ids of trainer symbols
+struct Word{
+    symbols: Vec<usize>,
+    count: usize,
+}
+
+impl Word {
+    /// adjacent pairs of symbols
+    fn pairs(&self) -> impl Iterator<Item=(usize, usize)> + '_{
+        self.symbols.windows(2).map(|pair|(pair[0], pair[1]))
+    }
+
+    /// merge each occurrence of pair into symbol from left to right
+    fn merge(&mut self, pair: (usize, usize), symbol: usize){
+        let mut i = 0;
+        while i + 1 < self.symbols.len(){
+            if (self.symbols[i], self.symbols[i + 1]) == pair{
+                self.symbols[i] = symbol;
+                self.symbols.remove(i + 1);
+            }
+            i += 1;
+        }
+    }
+}
+
+/// byte-pair-encoding trainer of train dataset, merges never cross the words of pre-tokenizer
+#[derive(Default)]
+pub(crate) struct BpeTrainer{
+    words: HashMap<String, usize>,
+}
+
+impl BpeTrainer {
+    /// count words of one document
+    pub fn add<'t>(&mut self, words: impl Iterator<Item=&'t str>){
+        words.for_each(|word|*self.words.entry(word.to_string()).or_insert(0) += 1);
+    }
+
+    /// learn merges until vocabulary reaches max_vocab_size including padding and unknown token,
+    /// pairs occur less than min_freq times are not merged. Chars are ranked by descending
+    /// frequency and merged tokens follow in merge order, padding token is always 0 and unknown
+    /// token is the last id
+    pub fn train(self, padding: &str, unknown: &str, max_vocab_size: usize, min_freq: usize) -> (HashMap<String, usize>, Merges){
+        let budget = max_vocab_size.saturating_sub(2);
+        let mut char_counts = HashMap::new();
+        self.words
+            .iter()
+            .for_each(|(word, count)|word.chars().for_each(|ch|*char_counts.entry(ch.to_string()).or_insert(0) += count));
+        let mut alphabet = char_counts.into_iter().collect::<Vec<_>>();
+        alphabet.sort_by(|a, b|b.1.cmp(&a.1).then_with(||a.0.cmp(&b.0)));
+        alphabet.truncate(budget);
+        let mut symbols = alphabet.into_iter().map(|(symbol, _)|symbol).collect::<Vec<_>>();
+        let mut symbol_ids = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)|(symbol.clone(), i))
+            .collect::<HashMap<_, _>>();
+        // words with a char out of alphabet are split at it and never merged across it
+        let mut words = Vec::new();
+        for (word, count) in &self.words{
+            let mut symbols = Vec::new();
+            for ch in word.chars(){
+                match symbol_ids.get(ch.encode_utf8(&mut [0; 4]) as &str){
+                    Some(id) => symbols.push(*id),
+                    None => words.push(Word{symbols: std::mem::take(&mut symbols), count: *count}),
+                }
+            }
+            words.push(Word{symbols, count: *count});
+        }
+        words.retain(|word|word.symbols.len() > 1);
+
+        let mut pair_counts = HashMap::<(usize, usize), usize>::new();
+        let mut pair_words = HashMap::<(usize, usize), HashSet<usize>>::new();
+        for (i, word) in words.iter().enumerate(){
+            for pair in word.pairs(){
+                *pair_counts.entry(pair).or_default() += word.count;
+                pair_words.entry(pair).or_default().insert(i);
+            }
+        }
+        // max heap of pair count, ties broken by the lexicographic order of pair
+        let key = |symbols: &[String], pair: (usize, usize), count: usize|
+            (count, Reverse((symbols[pair.0].clone(), symbols[pair.1].clone())), pair);
+        let mut heap = pair_counts
+            .iter()
+            .map(|(pair, count)|key(&symbols, *pair, *count))
+            .collect::<BinaryHeap<_>>();
+        let mut merges = Vec::new();
+        while symbols.len() < budget{
+            let (count, _, pair) = match heap.pop(){
+                Some(top) => top,
+                None => break,
+            };
+            let current = pair_counts.get(&pair).copied().unwrap_or(0);
+            if count != current{
+                // stale entry, each change of count pushes a fresh one
+                continue;
+            }
+            if count < min_freq.max(1){
+                break;
+            }
+            let merged = format!("{}{}", symbols[pair.0], symbols[pair.1]);
+            merges.push((symbols[pair.0].clone(), symbols[pair.1].clone()));
+            let symbol = match symbol_ids.get(&merged){
+                Some(id) => *id,
+                None => {
+                    symbols.push(merged.clone());
+                    symbol_ids.insert(merged, symbols.len() - 1);
+                    symbols.len() - 1
+                }
+            };
+            let mut changed = HashSet::new();
+            for i in pair_words.remove(&pair).unwrap_or_default(){
+                let word = &mut words[i];
+                // pairs kept by the merge lose the count too, so they need a fresh entry as well
+                for old in word.pairs().collect::<Vec<_>>(){
+                    *pair_counts.get_mut(&old).unwrap() -= word.count;
+                    changed.insert(old);
+                }
+                word.merge(pair, symbol);
+                for new in word.pairs().collect::<Vec<_>>(){
+                    *pair_counts.entry(new).or_default() += word.count;
+                    if new != pair{
+                        pair_words.entry(new).or_default().insert(i);
+                    }
+                    changed.insert(new);
+                }
+            }
+            pair_counts.remove(&pair);
+            for pair in changed{
+                if let Some(count) = pair_counts.get(&pair).filter(|count|**count > 0){
+                    heap.push(key(&symbols, pair, *count));
+                }
+            }
+        }
+        eprintln!("bpe learns {} merges of {} distinct words, vocabulary keeps {} tokens", merges.len(), self.words.len(), symbols.len() + 2);
+        let mut vocab = HashMap::new();
+        vocab.insert(padding.to_owned(), 0);
+        symbols
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, symbol)|{vocab.insert(symbol, i + 1);});
+        let len = vocab.len();
+        vocab.insert(unknown.to_owned(), len);
+        (vocab, merges)
+    }
+}
+
+/// byte-pair-encoding tokenizer, each word of pre-tokenizer is split into chars, then the adjacent
+/// pair of the lowest merge rank is merged until no pair can be merged
+pub(crate) struct BpeTokenizer{
+    pre_tokenizer: Box<dyn Tokenizer>,
+    vocab: HashMap<String, usize>,
+    /// rank of merge by the vocabulary ids of pair
+    ranks: HashMap<(usize, usize), usize>,
+}
+
+impl BpeTokenizer {
+    pub fn new(pre_tokenizer: Box<dyn Tokenizer>, vocab: &HashMap<String, usize>, merges: &[(String, String)]) -> Self{
+        let ranks = merges
+            .iter()
+            .enumerate()
+            .filter_map(|(rank, (a, b))|Some(((*vocab.get(a)?, *vocab.get(b)?), rank)))
+            .fold(HashMap::new(), |mut ranks, (pair, rank)|{
+                ranks.entry(pair).or_insert(rank);
+                ranks
+            });
+        Self{
+            pre_tokenizer,
+            vocab: vocab.clone(),
+            ranks,
+        }
+    }
+
+    /// pieces of word as byte ranges
+    fn split_word(&self, word: &str) -> Vec<(usize, usize)>{
+        let mut pieces = word
+            .char_indices()
+            .map(|(i, ch)|(i, i + ch.len_utf8()))
+            .collect::<Vec<_>>();
+        loop{
+            let lowest = pieces
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, pair)|{
+                    let a = self.vocab.get(&word[pair[0].0..pair[0].1])?;
+                    let b = self.vocab.get(&word[pair[1].0..pair[1].1])?;
+                    let rank = self.ranks.get(&(*a, *b))?;
+                    // merged token must be in vocabulary too
+                    self.vocab.get(&word[pair[0].0..pair[1].1])?;
+                    Some((*rank, i))
+                })
+                .min();
+            match lowest{
+                Some((_, i)) => {
+                    pieces[i].1 = pieces[i + 1].1;
+                    pieces.remove(i + 1);
+                },
+                None => return pieces,
+            }
+        }
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        self.pre_tokenizer
+            .tokenize(text)
+            .into_iter()
+            .flat_map(|word|{
+                let pieces = self.split_word(&word);
+                match word{
+                    Cow::Borrowed(word) => pieces
+                        .into_iter()
+                        .map(|(start, end)|Cow::Borrowed(&word[start..end]))
+                        .collect::<Vec<_>>(),
+                    Cow::Owned(word) => pieces
+                        .into_iter()
+                        .map(|(start, end)|Cow::Owned(word[start..end].to_string()))
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// load merges file, each line is a pair of tokens separated by a space
+pub(crate) fn load_merges(merges_file: &Path) -> Result<Merges, DatasetError>{
+    eprintln!("reader merges file from {}", merges_file.display());
+    let merges_reader = BufReader::new(open_file(merges_file)?);
+    let mut merges = Vec::new();
+    for (i, line) in merges_reader.lines().enumerate(){
+        let line = line.map_err(|source|DatasetError::io(merges_file, source))?;
+        if i == 0 && line.starts_with("#version"){
+            continue;
+        }
+        let (a, b) = line
+            .split_once(' ')
+            .ok_or_else(||DatasetError::invalid(merges_file, i + 1, column_after(&line), "space between merge pair is not found".to_string()))?;
+        merges.push((a.to_string(), b.to_string()));
+    }
+    Ok(merges)
+}
+
+/// save merges in rank order, the user merges file is kept as it is
+pub(crate) fn save_merges(merges: &[(String, String)], merges_file: &Path, user_files: &[PathBuf]) -> Result<(), DatasetError>{
+    if is_user_file(merges_file, user_files){
+        eprintln!("keep user merges file {}", merges_file.display());
+        return Ok(());
+    }
+    let lines = merges.iter().map(|(a, b)|format!("{} {}", a, b));
+    save_lines(merges_file, iter::once(MERGES_HEADER.to_string()).chain(lines))
+}
@@ -1,16 +1,24 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, Write, BufReader, BufWriter};
-use std::path::Path;
-use arrow::ipc::writer::FileWriter;
-use std::sync::Arc;
-use arrow::array::{ArrayRef, UInt8Array, UInt32Array};
-use arrow::datatypes::{Schema, Field, DataType};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use arrow::array::{ArrayRef, UInt32Array, UInt64Array};
+use arrow::datatypes::{Schema, SchemaRef, Field, DataType, UInt8Type};
 use arrow::record_batch::{RecordBatch};
 use rayon::prelude::*;
-use clap::Args;
-use crate::dataset::traits::IDataset;
-use indicatif::ProgressBar;
+use clap::{ArgAction, ArgEnum, Args};
+use regex::Regex;
+use crate::dataset::layout::{length_array, mask_arrays, sequence_arrays, sequence_fields, Layout};
+use crate::dataset::overflow::{check_stride, window_stride, windows, Overflow};
+use crate::dataset::id_type::{id_array, id_field, id_sequence_arrays, id_sequence_fields, resolve_id_type, IdType};
+use crate::dataset::error::{column_after, open_file, DatasetError, InvalidSample, OnError};
+use crate::dataset::bpe::{save_merges, Merges};
+use crate::dataset::tokenizer::{check_sequence_length, build_tokenizer, tokenization, tokenizer_type, Framing, Tokenizer, TokenizerOptions, TokenizerType};
+use crate::dataset::traits::{read_lines, IDataset, RawSamples};
+use crate::dataset::truncation::{Truncation, Truncator};
+use crate::dataset::vocab::{save_lines, save_vocab, VocabLimits};
+use crate::dataset::writer::{Compression, Format, WriterOptions};
 
 /// classifier args structure
 #[derive(Args, Debug)]
@@ -24,57 +32,181 @@ pub struct ClassifierArgs{
     /// with user vocabulary for classifier dataset
     #[clap(long)]
     with_vocab: bool,
-    /// max vocabulary size for build record, only effective when the with-vocab is not set
+    /// vocabulary file for build record, only effective when the with-vocab is set or the tokenizer is wordpiece, default to vocab.txt of dataset path
+    #[clap(long, visible_alias="vocab")]
+    vocab_file: Option<String>,
+    /// max vocabulary size including special tokens, keep the most frequent tokens of train dataset, only effective when the with-vocab is not set
     #[clap(long, default_value = "10000")]
     max_vocab_size: usize,
+    /// min frequency of token for build vocabulary, only effective when the with-vocab is not set
+    #[clap(long, default_value = "1")]
+    min_freq: usize,
+    /// max document frequency ratio of token in (0, 1] for build vocabulary, only effective when the with-vocab is not set
+    #[clap(long, default_value = "1.0")]
+    max_df: f64,
+    /// integer type of token id columns, auto picks the narrowest type holds the vocabulary
+    #[clap(long, arg_enum, default_value = "auto")]
+    token_type: IdType,
     /// max sequence length for sentence
     #[clap(long, default_value = "32")]
     sequence_length: usize,
+    /// strategy of which part of sentence to keep when truncating
+    #[clap(long, arg_enum, default_value = "head")]
+    truncation: Truncation,
+    /// head tokens to keep of head-tail truncation, default to half of the length left for sentence after special tokens
+    #[clap(long)]
+    head_length: Option<usize>,
     /// stopwords file for build vocabulary, only effective when the with-vocab is not set
     #[clap(long, visible_alias="stopwords")]
     stopwords_file: Option<String>,
     /// origin sample data use label_id instead of label
     #[clap(long)]
     with_label_id: bool,
-    /// with en language
+    /// integer type of class column, auto picks the narrowest type holds all classes of class.txt
+    #[clap(long, arg_enum, default_value = "auto")]
+    class_type: IdType,
+    /// multi-label sample whose labels are separated by label-delimiter, written as a multi-hot vector or a list of label ids
+    #[clap(long, arg_enum)]
+    multi_label: Option<MultiLabel>,
+    /// delimiter between labels of multi-label sample
+    #[clap(long, default_value = ",")]
+    label_delimiter: String,
+    /// order of classes inferred from train dataset when class.txt is absent, sorted by label or by descending frequency
+    #[clap(long, arg_enum, default_value = "sorted")]
+    class_order: ClassOrder,
+    /// tokenizer of sentence
+    #[clap(long, arg_enum, default_value = "char")]
+    tokenizer: TokenizerType,
+    /// pattern of tokens for regex tokenizer, default to words and single punctuations
+    #[clap(long)]
+    token_pattern: Option<Regex>,
+    /// tokenizer.json of HuggingFace tokenizers, overrides the tokenizer, vocabulary and special tokens
+    #[clap(long)]
+    tokenizer_file: Option<String>,
+    /// classification token starts each sentence of wordpiece tokenizer
+    #[clap(long, default_value = "[CLS]")]
+    cls_token: String,
+    /// separator token ends each sentence of wordpiece tokenizer
+    #[clap(long, default_value = "[SEP]")]
+    sep_token: String,
+    /// lowercase text of wordpiece tokenizer as uncased BERT models, false for cased ones
+    #[clap(long, default_value = "true", action = ArgAction::Set)]
+    lowercase: bool,
+    /// strip accents of text of wordpiece tokenizer, default to lowercase
+    #[clap(long, action = ArgAction::Set)]
+    strip_accents: Option<bool>,
+    /// merges file of bpe tokenizer, only effective when the with-vocab is set, default to merges.txt of dataset path
+    #[clap(long, visible_alias="merges")]
+    merges_file: Option<String>,
+    /// with en language, same as the whitespace tokenizer
     #[clap(long)]
     with_lang_en: bool,
     /// separator between sentence and label
     #[clap(long, short, visible_alias="delimiter", default_value = "\t")]
     separator: String,
-    /// unknown special token of vocabulary
-    #[clap(long, visible_alias = "unk-token", default_value = "<UNK>")]
+    /// unknown special token of vocabulary, default to [UNK] for wordpiece tokenizer
+    #[clap(long, visible_alias = "unk-token", default_value = "<UNK>", default_value_if("tokenizer", Some("wordpiece"), Some("[UNK]")))]
     unknown: String,
-    /// padding special token of vocabulary
-    #[clap(long, visible_alias = "pad-token", default_value = "<PAD>")]
+    /// padding special token of vocabulary, default to [PAD] for wordpiece tokenizer
+    #[clap(long, visible_alias = "pad-token", default_value = "<PAD>", default_value_if("tokenizer", Some("wordpiece"), Some("[PAD]")))]
     padding: String,
+    /// format of record file
+    #[clap(long, arg_enum, default_value = "ipc")]
+    format: Format,
+    /// compression of record file
+    #[clap(long, arg_enum)]
+    compression: Option<Compression>,
+    /// max rows of row group, only effective for parquet format
+    #[clap(long)]
+    row_group_size: Option<usize>,
+    /// write records to stdout one split after another, only supported by ipc-stream format
+    #[clap(long)]
+    stdout: bool,
+    /// samples read, encoded and written at a time, peak memory grows with it
+    #[clap(long, default_value = "100000")]
+    chunk_size: usize,
+    /// policy for invalid samples, skip and log write them to <split>.rejected.txt of output path, log also reports each error
+    #[clap(long, arg_enum, default_value = "fail")]
+    on_error: OnError,
+    /// layout of sequence columns in record file
+    #[clap(long, arg_enum, default_value = "columns")]
+    layout: Layout,
+    /// write line_no column of the source line (starts from 1) of each record
+    #[clap(long)]
+    with_line_no: bool,
+    /// write length column of sequence before padding
+    #[clap(long)]
+    with_length: bool,
+    /// write attention mask column of sequence
+    #[clap(long)]
+    with_attention_mask: bool,
+    /// strategy for sentence longer than sequence-length, split emits doc_id and window_index columns
+    #[clap(long, arg_enum, default_value = "truncate")]
+    overflow: Overflow,
+    /// step between windows of split overflow, default to sequence-length
+    #[clap(long)]
+    stride: Option<usize>,
+}
+
+/// class columns of multi-label sample
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultiLabel{
+    /// 0 or 1 for each class of class.txt, columns layout writes class_k columns and others write a fixed list
+    MultiHot,
+    /// list of label ids
+    List,
+}
+
+/// labels of raw sample, each with its column
+type Labels<'r> = Vec<(&'r str, usize)>;
+
+/// order of classes inferred from train dataset
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClassOrder{
+    /// lexicographic order of labels
+    Sorted,
+    /// descending frequency of labels, ties broken lexicographically
+    Frequency,
 }
 
 pub(crate) struct ClassifierRecord {
     word_ids: Vec<usize>,
-    label_id: usize,
+    label_ids: Vec<usize>,
+    doc_id: usize,
+    window_index: usize,
+    line_no: usize,
 }
 
 impl ClassifierRecord {
-    pub fn new(mut word_ids: Vec<usize>, label_id: usize, max_length: usize) -> Self{
-        if word_ids.len() > max_length{
-            let _ = word_ids.split_off(max_length);
-        }else if word_ids.len() < max_length {
-            let len = word_ids.len();
-            word_ids.append(&mut vec![0usize; max_length - len]);
-        }
+    pub fn new(word_ids: Vec<usize>, label_ids: Vec<usize>, line_no: usize) -> Self{
         Self{
             word_ids,
-            label_id
+            label_ids,
+            doc_id: 0,
+            window_index: 0,
+            line_no,
         }
     }
+
+    /// split document into windows of max_length, each window starts stride after the previous one
+    pub fn split(word_ids: Vec<usize>, label_ids: Vec<usize>, doc_id: usize, line_no: usize, max_length: usize, stride: usize) -> Vec<Self>{
+        windows(word_ids.len(), max_length, stride)
+            .into_iter()
+            .enumerate()
+            .map(|(window_index, range)|Self{
+                doc_id,
+                window_index,
+                ..Self::new(word_ids[range].to_vec(), label_ids.clone(), line_no)
+            })
+            .collect()
+    }
 }
 
-pub(crate) struct ClassifierSample(String, String);
+pub(crate) struct ClassifierSample(String, Vec<usize>, usize, usize);
 
 impl ClassifierSample {
-    pub fn new(sent: & str, label: & str) -> Self{
-        Self(sent.to_string(), label.to_string())
+    pub fn new(sent: & str, label_ids: Vec<usize>, doc_id: usize, line_no: usize) -> Self{
+        Self(sent.to_string(), label_ids, doc_id, line_no)
     }
 }
 
@@ -82,7 +214,22 @@ pub struct ClassifierBuilder<'a>{
     args:&'a ClassifierArgs,
     vocab: HashMap<String, usize>,
     classes: HashMap<String, usize>,
-    stopwords: HashSet<String>,
+    tokenizer: Box<dyn Tokenizer>,
+    /// `[CLS]` and `[SEP]` of wordpiece tokenizer
+    framing: Option<Framing>,
+    /// merges of bpe tokenizer
+    merges: Merges,
+    /// id of padding token, 0 except tokenizer file
+    pad_id: usize,
+    /// vocab and merges files given by user, kept as they are when saving vocabulary
+    user_files: Vec<PathBuf>,
+    token_type: IdType,
+    class_type: IdType,
+    overflowed: AtomicUsize,
+    /// classes are inferred from train dataset instead of class.txt
+    inferred: bool,
+    /// labels of current split absent from the inferred classes
+    unseen: Mutex<BTreeSet<String>>,
 }
 
 impl <'a>ClassifierBuilder<'a> {
@@ -91,153 +238,351 @@ impl <'a>ClassifierBuilder<'a> {
             args,
             vocab: HashMap::new(),
             classes: HashMap::new(),
-            stopwords: HashSet::new(),
+            tokenizer: build_tokenizer(tokenizer_type(args.tokenizer, args.with_lang_en), args.token_pattern.as_ref()),
+            framing: None,
+            merges: Vec::new(),
+            pad_id: 0,
+            user_files: Vec::new(),
+            token_type: args.token_type,
+            class_type: args.class_type,
+            overflowed: AtomicUsize::new(0),
+            inferred: false,
+            unseen: Mutex::new(BTreeSet::new()),
+        }
+    }
+    fn tokenizer_options(&self) -> TokenizerOptions<'_>{
+        TokenizerOptions{
+            tokenizer: Some(tokenizer_type(self.args.tokenizer, self.args.with_lang_en)),
+            token_pattern: self.args.token_pattern.as_ref(),
+            tokenizer_file: self.args.tokenizer_file.as_deref(),
+            cls_token: &self.args.cls_token,
+            sep_token: &self.args.sep_token,
+            lowercase: self.args.lowercase,
+            strip_accents: self.args.strip_accents,
+            path: self.get_input_path(),
+            with_vocab: self.args.with_vocab,
+            vocab_file: self.args.vocab_file.as_deref(),
+            merges_file: self.args.merges_file.as_deref(),
+            stopwords_file: self.args.stopwords_file.as_deref(),
+            padding: &self.args.padding,
+            unknown: &self.args.unknown,
+            limits: VocabLimits{
+                max_vocab_size: self.args.max_vocab_size,
+                min_freq: self.args.min_freq,
+                max_df: self.args.max_df,
+            },
+            token_type: self.args.token_type,
+        }
+    }
+    /// id of label, label is the id itself with with-label-id, which must be a class of class.txt
+    fn label_id(&self, label: &str, line_no: usize, column: usize) -> Result<usize, InvalidSample>{
+        if !self.args.with_label_id{
+            return match self.classes.get(label){
+                Some(label_id) => Ok(*label_id),
+                None if self.inferred => {
+                    self.unseen.lock().unwrap().insert(label.to_string());
+                    Err(InvalidSample::new(line_no, column, format!("label `{}` is not found in train dataset", label)))
+                },
+                None => Err(InvalidSample::new(line_no, column, format!("label `{}` is not found in class.txt", label)))
+            };
         }
+        let label_id = label
+            .parse()
+            .map_err(|_|InvalidSample::new(line_no, column, format!("label id `{}` is not an integer", label)))?;
+        // class type holds every class of class.txt, so a label id of class.txt never overflows it
+        if label_id >= self.classes.len(){
+            return Err(InvalidSample::new(line_no, column, format!("label id {} is not found in class.txt", label_id)));
+        }
+        Ok(label_id)
+    }
+    /// sentence and labels of raw sample, each label comes with its column
+    fn split_sample<'r>(&self, line_no: usize, raw: &'r str) -> Result<(&'r str, Labels<'r>), InvalidSample>{
+        let (sent, label) = raw
+            .split_once(&self.args.separator)
+            .ok_or_else(||InvalidSample::new(line_no, column_after(raw), format!("separator {:?} is not found", self.args.separator)))?;
+        let column = column_after(&raw[..raw.len() - label.len()]);
+        let labels = match self.args.multi_label{
+            None => vec![(label, column)],
+            Some(_) => {
+                let delimiter = self.args.label_delimiter.as_str();
+                let mut offset = 0;
+                label
+                    .split(delimiter)
+                    .map(|item|{
+                        let item_column = column + label[..offset].chars().count();
+                        offset += item.len() + delimiter.len();
+                        (item, item_column)
+                    })
+                    .collect()
+            }
+        };
+        Ok((sent, labels))
+    }
+    /// classes of train dataset, in the order of class-order
+    fn infer_classes(&self) -> Result<HashMap<String, usize>, DatasetError>{
+        let train_file = self.get_input_path().join("train.txt");
+        eprintln!("class.txt is not found, infer classes from {}", train_file.display());
+        let mut counts = HashMap::new();
+        for raw in self.read_dataset(&train_file)?{
+            let (line_no, raw) = raw?;
+            match self.split_sample(line_no, &raw){
+                Ok((_, labels)) => labels
+                    .into_iter()
+                    .for_each(|(label, _)|*counts.entry(label.to_string()).or_insert(0) += 1),
+                // invalid samples are rejected again when the train dataset is saved
+                Err(_) if self.args.on_error != OnError::Fail => (),
+                Err(sample) => return Err(DatasetError::Invalid{file: train_file, sample}),
+            }
+        }
+        let mut classes = counts.into_iter().collect::<Vec<_>>();
+        match self.args.class_order{
+            ClassOrder::Sorted => classes.sort(),
+            ClassOrder::Frequency => classes.sort_by(|a, b|b.1.cmp(&a.1).then_with(||a.0.cmp(&b.0))),
+        }
+        Ok(classes
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, _))|(label, i))
+            .collect())
+    }
+    /// save classes in id order, one label each line as class.txt
+    fn save_classes(&self) -> Result<(), DatasetError>{
+        let mut classes = self.classes.iter().collect::<Vec<_>>();
+        classes.sort_by_key(|(_, idx)|**idx);
+        save_lines(&self.get_output_path().join("class.txt"), classes.into_iter().map(|(label, _)|label.to_string()))
+    }
+    /// multi-hot vector has a fixed length, so list layout writes it as a fixed list too
+    fn multi_hot_layout(&self) -> Layout{
+        match self.args.layout{
+            Layout::Columns => Layout::Columns,
+            _ => Layout::FixedList,
+        }
+    }
+    /// max tokens of sentence, framing tokens are added after truncation
+    fn max_length(&self) -> usize{
+        self.args.sequence_length.saturating_sub(self.framing.as_ref().map_or(0, Framing::length))
     }
 }
 
 impl<'a> IDataset<ClassifierSample, ClassifierRecord> for ClassifierBuilder<'a> {
 
-    fn init(&mut self, train_samples: & Vec<ClassifierSample>){
-        let base_path = Path::new(&self.args.path);
-        let classes_file = base_path.join("class.txt");
-        let class_reader = BufReader::new(File::open(classes_file).unwrap());
-        class_reader
-            .lines()
-            .filter_map(Result::ok)
-            .enumerate()
-            .for_each(|(i, label)|{
+    fn init(&mut self) -> Result<(), DatasetError>{
+        let mut classes_file = self.get_input_path().join("class.txt");
+        if classes_file.exists() || self.args.with_label_id{
+            let class_reader = BufReader::new(open_file(&classes_file)?);
+            for (i, label) in class_reader.lines().enumerate(){
+                let label = label.map_err(|source|DatasetError::io(&classes_file, source))?;
                 self.classes.insert(label, i);
-            });
-        if let Some(stopwords_file) = &self.args.stopwords_file{
-            println!("reader stopwords file from {}", stopwords_file);
-            let stopwords_reader = BufReader::new(File::open(stopwords_file).expect("open stopwords file failed"));
-            stopwords_reader
-                .lines()
-                .filter_map(Result::ok)
-                .for_each(|word|{
-                    self.stopwords.insert(word);
-                })
-        }
-        self.vocab.insert(self.args.padding.to_owned(), 0);
-        let mut vocab = HashSet::new();
-        train_samples
-            .iter()
-            .for_each(|item|if self.args.with_lang_en{
-                item.0
-                    .split(' ')
-                    .for_each(|word|{vocab.insert(word.to_string());})
-            }else {
-                item.0
-                    .chars()
-                    .for_each(|ch|{vocab.insert(ch.to_string());})
             }
-            );
-        vocab
+        }else {
+            self.classes = self.infer_classes()?;
+            self.inferred = true;
+            self.save_classes()?;
+            classes_file = self.get_input_path().join("train.txt");
+        }
+        self.class_type = resolve_id_type(self.args.class_type, self.classes.len(), "classes", "class", &classes_file)?;
+        let tokenization = tokenization(&self.tokenizer_options(), |tokenizer, add|{
+            for samples in self.read_chunks("train.txt", |_, _|Ok(()))?{
+                samples?
+                    .iter()
+                    .for_each(|sample|add(&tokenizer.tokenize(&sample.0)));
+            }
+            Ok(())
+        })?;
+        self.tokenizer = tokenization.tokenizer;
+        self.vocab = tokenization.vocab;
+        self.framing = tokenization.framing;
+        self.merges = tokenization.merges;
+        self.pad_id = tokenization.pad_id;
+        self.user_files = tokenization.user_files;
+        self.token_type = tokenization.token_type;
+        check_sequence_length(self.framing.as_ref(), self.args.sequence_length)?;
+        check_stride(self.args.overflow, self.args.stride, self.max_length())?;
+        Ok(())
+    }
+
+    fn read_dataset(&self, file: &Path) -> Result<RawSamples<'_>, DatasetError> {
+        read_lines(file)
+    }
+
+    fn parse_sample(&self, index: usize, line_no: usize, raw: &str) -> Result<ClassifierSample, InvalidSample> {
+        let (sent, labels) = self.split_sample(line_no, raw)?;
+        let mut label_ids = labels
             .into_iter()
-            .filter(|word|!self.stopwords.contains(word))
-            .enumerate()
-            .for_each(|(i, word)|{self.vocab.insert(word, i + 1);});
-        let len = self.vocab.len();
-        self.vocab.insert(self.args.unknown.to_owned(), len);
-    }
-
-    fn read_dataset(&self, file: &str) -> Vec<ClassifierSample> {
-        let base_path = Path::new(&self.args.path);
-        let data_file = base_path.join(file);
-        let data_reader = BufReader::new(File::open(data_file).unwrap());
-        data_reader
-            .lines()
-            .par_bridge()
-            .filter_map(Result::ok)
-            .map(|line|line
-                .split_once(&self.args.separator)
-                .map(|item|ClassifierSample::new(item.0, item.1)).unwrap()
-            )
-            .collect()
+            .map(|(label, column)|self.label_id(label, line_no, column))
+            .collect::<Result<Vec<_>, _>>()?;
+        label_ids.sort_unstable();
+        label_ids.dedup();
+        Ok(ClassifierSample::new(sent, label_ids, index, line_no))
     }
 
     fn build_dataset(&self, samples: Vec<ClassifierSample>) -> Vec<ClassifierRecord> {
-        let max_length = self.args.sequence_length;
-        let unk_id = self.vocab.get(&self.args.unknown).unwrap();
-        let pb = ProgressBar::new(samples.len() as u64);
-        let records = samples
+        let max_length = self.max_length();
+        let stride = window_stride(self.args.stride, max_length);
+        let truncator = Truncator::new(self.args.truncation, self.args.head_length);
+        // vocabulary of tokenizer file may have no unknown token, tokens out of it are dropped
+        let unk_id = self.vocab.get(&self.args.unknown).copied();
+        samples
             .into_par_iter()
             .map(|sample|{
-                pb.inc(1);
-                if self.args.with_lang_en{
-                    let word_ids = sample.0
-                        .split(' ')
-                        .map(|word| self.vocab
-                            .get(word).map(|it| *it)
-                            .unwrap_or(*unk_id)
-                        ).collect::<Vec<_>>();
-                    (word_ids, sample.1)
-                }else {
-                    let word_ids = sample.0
-                        .chars()
-                        .map(|ch| self.vocab
-                            .get(&ch.to_string()).map(|it| *it)
-                            .unwrap_or(*unk_id)
-                        ).collect::<Vec<_>>();
-                    (word_ids, sample.1)
+                let word_ids = self.tokenizer
+                    .tokenize(&sample.0)
+                    .iter()
+                    .filter_map(|token| self.vocab
+                        .get(token.as_ref()).copied()
+                        .or(unk_id)
+                    ).collect::<Vec<_>>();
+                (sample.2, sample.3, word_ids, sample.1)
+            })
+            .flat_map_iter(|(doc_id, line_no, mut word_ids, label_ids)|{
+                if word_ids.len() > max_length{
+                    self.overflowed.fetch_add(1, Ordering::Relaxed);
+                }
+                match self.args.overflow{
+                    Overflow::Split => ClassifierRecord::split(word_ids, label_ids, doc_id, line_no, max_length, stride),
+                    Overflow::Drop if word_ids.len() > max_length => vec![],
+                    _ => {
+                        truncator.truncate(&mut word_ids, max_length);
+                        vec![ClassifierRecord::new(word_ids, label_ids, line_no)]
+                    },
                 }
             })
-            .map(|(word_ids, label)|{
-                if self.args.with_label_id{
-                    ClassifierRecord::new(word_ids, label.parse().unwrap(), max_length)
-                }else {
-                    let label_id = self.classes.get(&label).unwrap();
-                    ClassifierRecord::new(word_ids, *label_id, max_length)
+            .map(|mut record|{
+                if let Some(framing) = &self.framing{
+                    framing.frame(&mut record.word_ids);
                 }
-            }).collect::<Vec<_>>();
-        pb.finish_with_message("done");
-        records
+                record
+            }).collect()
     }
-    fn save_dataset(&self, records: Vec<ClassifierRecord>, record_file: &str) {
-        let output_path = self.get_output_path();
+    fn schema(&self) -> SchemaRef{
         let max_length = self.args.sequence_length;
-        let mut fields = Vec::new();
-        for k in 0..max_length{
-            let field = Field::new(&format!("word_{}", k), DataType::UInt32, false);
-            fields.push(field);
-        }
-        let field = Field::new("class", DataType::UInt8, false);
-        fields.push(field);
-        let schema = Arc::new(Schema::new(fields));
-        let record_file = File::create(output_path.join(record_file)).expect(&format!("create record file {} failed", record_file));
-        let mut writer = FileWriter::try_new(record_file, &schema).expect("create file writer failed");
-        for chunk in records.chunks(100){
-            let mut values = Vec::new();
-            for i in 0..max_length{
-                let series = chunk
+        let layout = self.args.layout;
+        let mut fields = id_sequence_fields(self.token_type, layout, "word", "input_ids", max_length);
+        match self.args.multi_label{
+            None => {
+                let field = id_field(self.class_type, "class");
+                fields.push(field);
+            },
+            Some(MultiLabel::MultiHot) => fields.append(&mut sequence_fields::<UInt8Type>(self.multi_hot_layout(), "class", "classes", self.classes.len())),
+            Some(MultiLabel::List) => fields.append(&mut id_sequence_fields(self.class_type, Layout::List, "class", "classes", 0)),
+        }
+        if self.args.overflow == Overflow::Split{
+            fields.push(Field::new("doc_id", DataType::UInt32, false));
+            fields.push(Field::new("window_index", DataType::UInt32, false));
+        }
+        if self.args.with_line_no{
+            fields.push(Field::new("line_no", DataType::UInt64, false));
+        }
+        if self.args.with_length{
+            fields.push(Field::new("length", DataType::UInt32, false));
+        }
+        if self.args.with_attention_mask{
+            fields.append(&mut sequence_fields::<UInt8Type>(layout, "mask", "attention_mask", max_length));
+        }
+        let metadata = HashMap::from([("token_type".to_string(), self.token_type.name().to_string())]);
+        Arc::new(Schema::new(fields).with_metadata(metadata))
+    }
+    fn record_batch(&self, schema: SchemaRef, records: &[ClassifierRecord]) -> RecordBatch{
+        let max_length = self.args.sequence_length;
+        let layout = self.args.layout;
+        let word_ids = records
+            .iter()
+            .map(|item|item.word_ids.as_slice())
+            .collect::<Vec<_>>();
+        let mut values = id_sequence_arrays(self.token_type, layout, &word_ids, max_length, self.pad_id);
+        let label_ids = records
+            .iter()
+            .map(|item|item.label_ids.as_slice())
+            .collect::<Vec<_>>();
+        match self.args.multi_label{
+            None => {
+                let label_ids = label_ids
                     .iter()
-                    .map(|item|item.word_ids[i] as u32)
-                    .collect::<Vec<u32>>();
-                values.push(Arc::new(UInt32Array::from(series)) as ArrayRef);
-            }
-            let label_ids = chunk
+                    .map(|label_ids|label_ids[0])
+                    .collect::<Vec<_>>();
+                values.push(id_array(self.class_type, &label_ids));
+            },
+            Some(MultiLabel::MultiHot) => {
+                let classes = self.classes.len();
+                let hots = label_ids
+                    .iter()
+                    .map(|label_ids|{
+                        let mut hot = vec![0; classes];
+                        label_ids.iter().for_each(|label_id|hot[*label_id] = 1);
+                        hot
+                    })
+                    .collect::<Vec<_>>();
+                let hots = hots.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                values.append(&mut sequence_arrays::<UInt8Type>(self.multi_hot_layout(), &hots, classes, 0));
+            },
+            Some(MultiLabel::List) => values.append(&mut id_sequence_arrays(self.class_type, Layout::List, &label_ids, 0, 0)),
+        }
+        if self.args.overflow == Overflow::Split{
+            let doc_ids = records
+                .iter()
+                .map(|item|item.doc_id as u32)
+                .collect::<Vec<u32>>();
+            values.push(Arc::new(UInt32Array::from(doc_ids)) as ArrayRef);
+            let window_indexes = records
                 .iter()
-                .map(|item|item.label_id as u8)
-                .collect::<Vec<u8>>();
-            values.push(Arc::new(UInt8Array::from(label_ids)) as ArrayRef);
-            let batch = RecordBatch::try_new(schema.clone(), values).expect("build batch error");
-            writer.write(&batch).expect("write record error");
+                .map(|item|item.window_index as u32)
+                .collect::<Vec<u32>>();
+            values.push(Arc::new(UInt32Array::from(window_indexes)) as ArrayRef);
+        }
+        if self.args.with_line_no{
+            let line_nos = records
+                .iter()
+                .map(|item|item.line_no as u64)
+                .collect::<Vec<u64>>();
+            values.push(Arc::new(UInt64Array::from(line_nos)) as ArrayRef);
+        }
+        if self.args.with_length{
+            values.push(length_array(&word_ids));
         }
-        writer.finish().expect("finish write records error");
+        if self.args.with_attention_mask{
+            values.append(&mut mask_arrays(layout, &word_ids, max_length));
+        }
+        RecordBatch::try_new(schema, values).expect("build batch error")
     }
-    fn save_vocab(&self){
-        let output_path = self.get_output_path();
-        let vocab_file = File::create(output_path.join("vocab.txt")).expect("create vocab file failed");
-        let mut writer = BufWriter::new(vocab_file);
-        for (word, idx) in &self.vocab{
-            writeln!(&mut writer, "{}\t{}", idx, word).expect("write vocab line failed");
+    fn writer_options(&self) -> WriterOptions{
+        WriterOptions{
+            format: self.args.format,
+            compression: self.args.compression,
+            row_group_size: self.args.row_group_size,
+            stdout: self.args.stdout,
         }
     }
+    fn chunk_size(&self) -> usize{
+        self.args.chunk_size
+    }
+    fn on_error(&self) -> OnError{
+        self.args.on_error
+    }
+    fn get_input_path(&self) -> &Path{
+        Path::new(&self.args.path)
+    }
+    fn save_vocab(&self) -> Result<(), DatasetError>{
+        save_vocab(&self.vocab, &self.get_output_path().join("vocab.txt"), &self.user_files)?;
+        if !self.merges.is_empty(){
+            save_merges(&self.merges, &self.get_output_path().join("merges.txt"), &self.user_files)?;
+        }
+        Ok(())
+    }
     fn get_output_path(&self) -> &Path {
         match &self.args.output_path{
             None => Path::new(&self.args.path),
             Some(output_path) => Path::new(output_path)
         }
     }
+    fn report(&self, split: & str){
+        let overflowed = self.overflowed.swap(0, Ordering::Relaxed);
+        if overflowed > 0{
+            eprintln!("{} {} sentences are longer than {}, overflow with {:?}", overflowed, split, self.args.sequence_length, self.args.overflow);
+        }
+        let unseen = std::mem::take(&mut *self.unseen.lock().unwrap());
+        if !unseen.is_empty(){
+            let unseen = unseen.into_iter().collect::<Vec<_>>();
+            eprintln!("{} labels of {} are absent from train dataset: {}", unseen.len(), split, unseen.join(", "));
+        }
+    }
 }
\ No newline at end of file
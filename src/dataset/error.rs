@@ -0,0 +1,90 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use clap::ArgEnum;
+
+/// policy for samples which are failed to parse
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnError{
+    /// stop at the first invalid sample
+    Fail,
+    /// skip invalid samples and write them to rejected file
+    Skip,
+    /// skip invalid samples, write them to rejected file and report each error
+    Log,
+}
+
+/// invalid sample at line and column of its file, both start from 1
+#[derive(Debug)]
+pub struct InvalidSample{
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl InvalidSample {
+    pub fn new(line: usize, column: usize, message: String) -> Self{
+        Self{
+            line,
+            column,
+            message
+        }
+    }
+}
+
+/// error of building dataset with the file it comes from
+#[derive(Debug)]
+pub enum DatasetError{
+    Io{file: PathBuf, source: io::Error},
+    Invalid{file: PathBuf, sample: InvalidSample},
+    /// invalid content of file which is not located at a line
+    Content{file: PathBuf, message: String},
+    /// invalid value of command line arg
+    Arg{name: &'static str, message: String},
+}
+
+impl Display for DatasetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self{
+            DatasetError::Io{file, source} => write!(f, "{}: {}", file.display(), source),
+            DatasetError::Invalid{file, sample} => write!(f, "{}:{}:{}: {}", file.display(), sample.line, sample.column, sample.message),
+            DatasetError::Content{file, message} => write!(f, "{}: {}", file.display(), message),
+            DatasetError::Arg{name, message} => write!(f, "--{}: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self{
+            DatasetError::Io{source, ..} => Some(source),
+            DatasetError::Invalid{..} | DatasetError::Content{..} | DatasetError::Arg{..} => None,
+        }
+    }
+}
+
+impl DatasetError {
+    pub fn io(file: &Path, source: io::Error) -> Self{
+        DatasetError::Io{file: file.to_path_buf(), source}
+    }
+    pub fn invalid(file: &Path, line: usize, column: usize, message: String) -> Self{
+        DatasetError::Invalid{file: file.to_path_buf(), sample: InvalidSample::new(line, column, message)}
+    }
+    pub fn content(file: &Path, message: String) -> Self{
+        DatasetError::Content{file: file.to_path_buf(), message}
+    }
+    pub fn arg(name: &'static str, message: String) -> Self{
+        DatasetError::Arg{name, message}
+    }
+}
+
+/// open file for read, error with the file path
+pub(crate) fn open_file(file: &Path) -> Result<File, DatasetError>{
+    File::open(file).map_err(|source|DatasetError::io(file, source))
+}
+
+/// column (starts from 1) of the char after `prefix`
+pub(crate) fn column_after(prefix: &str) -> usize{
+    prefix.chars().count() + 1
+}
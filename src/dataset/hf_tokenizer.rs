@@ -0,0 +1,810 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::path::Path;
+use regex::{NoExpand, Regex};
+use serde_json::Value;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use crate::dataset::error::{open_file, DatasetError};
+use crate::dataset::tokenizer::{is_cjk, is_control, is_punctuation, is_whitespace, remove_accents, Framing, Tokenizer};
+
+/// pattern of ByteLevel pre-tokenizer, the same as GPT-2
+const BYTE_LEVEL_PATTERN: &str = r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+/// the only lookaround supported in patterns of tokenizer.json, emulated after matching
+const TRAILING_SPACE_LOOKAHEAD: &str = r"\s+(?!\S)";
+
+/// error of tokenizer.json content
+type Invalid = String;
+
+/// pattern of Split pre-tokenizer and Replace normalizer
+enum Pattern{
+    Literal(String),
+    /// regex and whether it emulates `\s+(?!\S)` by the `ws` group
+    Regex(Regex, bool),
+}
+
+impl Pattern {
+    fn parse(value: &Value) -> Result<Self, Invalid>{
+        if let Some(literal) = value.get("String").and_then(Value::as_str){
+            return Ok(Pattern::Literal(literal.to_string()));
+        }
+        let pattern = value
+            .get("Regex")
+            .and_then(Value::as_str)
+            .ok_or_else(||format!("pattern {} is neither String nor Regex", value))?;
+        Self::regex(pattern)
+    }
+
+    fn regex(pattern: &str) -> Result<Self, Invalid>{
+        let lookahead = pattern.contains(TRAILING_SPACE_LOOKAHEAD);
+        let pattern = pattern.replace(TRAILING_SPACE_LOOKAHEAD, r"(?P<ws>\s+)");
+        Regex::new(&pattern)
+            .map(|regex|Pattern::Regex(regex, lookahead))
+            .map_err(|err|format!("regex `{}` is not supported: {}", pattern, err))
+    }
+
+    /// byte ranges of matches
+    fn find(&self, text: &str) -> Vec<(usize, usize)>{
+        match self{
+            Pattern::Literal(literal) if literal.is_empty() => vec![],
+            Pattern::Literal(literal) => text
+                .match_indices(literal.as_str())
+                .map(|(start, matched)|(start, start + matched.len()))
+                .collect(),
+            Pattern::Regex(regex, false) => regex
+                .find_iter(text)
+                .filter(|found|!found.is_empty())
+                .map(|found|(found.start(), found.end()))
+                .collect(),
+            Pattern::Regex(regex, true) => {
+                let mut matches = Vec::new();
+                let mut start = 0;
+                while let Some(captures) = regex.captures_at(text, start){
+                    let found = captures.get(0).unwrap();
+                    let mut end = found.end();
+                    // `\s+(?!\S)` leaves the last space of a run followed by a non-space
+                    if captures.name("ws").is_some() && text[end..].chars().next().is_some_and(|ch|!ch.is_whitespace()){
+                        let last = text[found.start()..end].chars().next_back().unwrap();
+                        if end - last.len_utf8() > found.start(){
+                            end -= last.len_utf8();
+                        }
+                    }
+                    if end > found.start(){
+                        matches.push((found.start(), end));
+                        start = end;
+                    }else {
+                        start = end + text[end..].chars().next().map_or(1, char::len_utf8);
+                    }
+                    if start > text.len(){
+                        break;
+                    }
+                }
+                matches
+            }
+        }
+    }
+}
+
+/// how matches of pre-tokenizer split a piece
+#[derive(Clone, Copy)]
+enum Behavior{
+    Removed,
+    Isolated,
+    MergedWithPrevious,
+    MergedWithNext,
+    Contiguous,
+}
+
+impl Behavior {
+    fn parse(value: Option<&Value>) -> Result<Self, Invalid>{
+        match value.and_then(Value::as_str).unwrap_or("Isolated"){
+            "Removed" => Ok(Behavior::Removed),
+            "Isolated" => Ok(Behavior::Isolated),
+            "MergedWithPrevious" => Ok(Behavior::MergedWithPrevious),
+            "MergedWithNext" => Ok(Behavior::MergedWithNext),
+            "Contiguous" => Ok(Behavior::Contiguous),
+            other => Err(format!("split behavior `{}` is not supported", other)),
+        }
+    }
+
+    /// pieces of text split by the sorted byte ranges of matches
+    fn split(self, text: &str, matches: &[(usize, usize)]) -> Vec<String>{
+        self.split_segments(text, segments(text, matches))
+    }
+
+    /// pieces of segments covering text, a segment is a byte range and whether it is a match
+    fn split_segments(self, text: &str, segments: Vec<(usize, usize, bool)>) -> Vec<String>{
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut previous_match = false;
+        match self{
+            Behavior::Removed => ranges.extend(segments
+                .into_iter()
+                .filter(|segment|!segment.2)
+                .map(|(start, end, _)|(start, end))),
+            Behavior::Isolated => ranges.extend(segments.into_iter().map(|(start, end, _)|(start, end))),
+            Behavior::MergedWithPrevious => for (start, end, is_match) in segments{
+                match ranges.last_mut(){
+                    Some(last) if is_match && !previous_match => last.1 = end,
+                    _ => ranges.push((start, end)),
+                }
+                previous_match = is_match;
+            },
+            Behavior::MergedWithNext => {
+                for (start, end, is_match) in segments.into_iter().rev(){
+                    match ranges.last_mut(){
+                        Some(last) if is_match && !previous_match => last.0 = start,
+                        _ => ranges.push((start, end)),
+                    }
+                    previous_match = is_match;
+                }
+                ranges.reverse();
+            },
+            Behavior::Contiguous => for (start, end, is_match) in segments{
+                match ranges.last_mut(){
+                    Some(last) if is_match == previous_match => last.1 = end,
+                    _ => ranges.push((start, end)),
+                }
+                previous_match = is_match;
+            },
+        }
+        ranges
+            .into_iter()
+            .filter(|(start, end)|start < end)
+            .map(|(start, end)|text[start..end].to_string())
+            .collect()
+    }
+}
+
+/// byte ranges of each char matches the predicate
+fn char_matches(text: &str, predicate: impl Fn(char) -> bool) -> Vec<(usize, usize)>{
+    text.char_indices()
+        .filter(|(_, ch)|predicate(*ch))
+        .map(|(i, ch)|(i, i + ch.len_utf8()))
+        .collect()
+}
+
+/// matches and the gaps between them in order
+fn segments(text: &str, matches: &[(usize, usize)]) -> Vec<(usize, usize, bool)>{
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for &(match_start, match_end) in matches{
+        if match_start > start{
+            segments.push((start, match_start, false));
+        }
+        segments.push((match_start, match_end, true));
+        start = match_end;
+    }
+    if start < text.len(){
+        segments.push((start, text.len(), false));
+    }
+    segments
+}
+
+/// segments with gaps as matches
+fn invert(segments: Vec<(usize, usize, bool)>) -> Vec<(usize, usize, bool)>{
+    segments
+        .into_iter()
+        .map(|(start, end, is_match)|(start, end, !is_match))
+        .collect()
+}
+
+enum Normalizer{
+    Bert{clean_text: bool, handle_chinese_chars: bool, strip_accents: bool, lowercase: bool},
+    Lowercase,
+    Nfd,
+    Nfc,
+    Nfkd,
+    Nfkc,
+    StripAccents,
+    Strip{left: bool, right: bool},
+    Replace{pattern: Pattern, content: String},
+    Prepend(String),
+    Sequence(Vec<Normalizer>),
+}
+
+impl Normalizer {
+    fn parse(value: &Value) -> Result<Self, Invalid>{
+        let flag = |name: &str, default: bool|value.get(name).and_then(Value::as_bool).unwrap_or(default);
+        match type_of(value)?{
+            "BertNormalizer" => {
+                let lowercase = flag("lowercase", true);
+                Ok(Normalizer::Bert{
+                    clean_text: flag("clean_text", true),
+                    handle_chinese_chars: flag("handle_chinese_chars", true),
+                    // accents are stripped with lowercase unless it is set
+                    strip_accents: flag("strip_accents", lowercase),
+                    lowercase,
+                })
+            },
+            "Lowercase" => Ok(Normalizer::Lowercase),
+            "NFD" => Ok(Normalizer::Nfd),
+            "NFC" => Ok(Normalizer::Nfc),
+            "NFKD" => Ok(Normalizer::Nfkd),
+            "NFKC" => Ok(Normalizer::Nfkc),
+            "StripAccents" => Ok(Normalizer::StripAccents),
+            "Strip" => Ok(Normalizer::Strip{left: flag("strip_left", true), right: flag("strip_right", true)}),
+            "Replace" => Ok(Normalizer::Replace{
+                pattern: Pattern::parse(field(value, "pattern")?)?,
+                content: string_field(value, "content")?.to_string(),
+            }),
+            "Prepend" => Ok(Normalizer::Prepend(string_field(value, "prepend")?.to_string())),
+            "Sequence" => array_field(value, "normalizers")?
+                .iter()
+                .map(Normalizer::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Normalizer::Sequence),
+            other => Err(format!("normalizer `{}` is not supported", other)),
+        }
+    }
+
+    fn normalize(&self, text: String) -> String{
+        match self{
+            Normalizer::Bert{clean_text, handle_chinese_chars, strip_accents, lowercase} => {
+                let mut text = text;
+                if *clean_text{
+                    text = text
+                        .chars()
+                        .filter(|ch|*ch != '\0' && *ch != '\u{FFFD}' && !is_control(*ch))
+                        .map(|ch|if is_whitespace(ch) {' '} else {ch})
+                        .collect();
+                }
+                if *handle_chinese_chars{
+                    text = text
+                        .chars()
+                        .flat_map(|ch|if is_cjk(ch) {vec![' ', ch, ' ']} else {vec![ch]})
+                        .collect();
+                }
+                if *strip_accents{
+                    text = remove_accents(&text);
+                }
+                if *lowercase{
+                    text = text.to_lowercase();
+                }
+                text
+            },
+            Normalizer::Lowercase => text.to_lowercase(),
+            Normalizer::Nfd => text.nfd().collect(),
+            Normalizer::Nfc => text.nfc().collect(),
+            Normalizer::Nfkd => text.nfkd().collect(),
+            Normalizer::Nfkc => text.nfkc().collect(),
+            Normalizer::StripAccents => text.chars().filter(|ch|!is_combining_mark(*ch)).collect(),
+            Normalizer::Strip{left, right} => {
+                let text = if *left {text.trim_start()} else {&text};
+                let text = if *right {text.trim_end()} else {text};
+                text.to_string()
+            },
+            Normalizer::Replace{pattern: Pattern::Literal(literal), content} => text.replace(literal.as_str(), content),
+            Normalizer::Replace{pattern: Pattern::Regex(regex, _), content} => regex.replace_all(&text, NoExpand(content)).into_owned(),
+            Normalizer::Prepend(prepend) if !text.is_empty() => format!("{}{}", prepend, text),
+            Normalizer::Prepend(_) => text,
+            Normalizer::Sequence(normalizers) => normalizers
+                .iter()
+                .fold(text, |text, normalizer|normalizer.normalize(text)),
+        }
+    }
+}
+
+/// when metaspace prepends the replacement
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PrependScheme{
+    Always,
+    First,
+    Never,
+}
+
+enum PreTokenizer{
+    Bert,
+    Whitespace(Pattern),
+    WhitespaceSplit,
+    Punctuation(Behavior),
+    Digits{individual: bool},
+    CharDelimiterSplit(char),
+    Metaspace{replacement: char, prepend_scheme: PrependScheme, split: bool},
+    ByteLevel{add_prefix_space: bool, pattern: Option<Pattern>},
+    Split{pattern: Pattern, behavior: Behavior, invert: bool},
+    Sequence(Vec<PreTokenizer>),
+}
+
+impl PreTokenizer {
+    fn parse(value: &Value) -> Result<Self, Invalid>{
+        let flag = |name: &str, default: bool|value.get(name).and_then(Value::as_bool).unwrap_or(default);
+        match type_of(value)?{
+            "BertPreTokenizer" => Ok(PreTokenizer::Bert),
+            "Whitespace" => Ok(PreTokenizer::Whitespace(Pattern::regex(r"\w+|[^\w\s]+")?)),
+            "WhitespaceSplit" => Ok(PreTokenizer::WhitespaceSplit),
+            "Punctuation" => Ok(PreTokenizer::Punctuation(Behavior::parse(value.get("behavior"))?)),
+            "Digits" => Ok(PreTokenizer::Digits{individual: flag("individual_digits", false)}),
+            "CharDelimiterSplit" => Ok(PreTokenizer::CharDelimiterSplit(char_field(value, "delimiter")?)),
+            "Metaspace" => {
+                let prepend_scheme = match value.get("prepend_scheme").and_then(Value::as_str){
+                    Some("always") => PrependScheme::Always,
+                    Some("first") => PrependScheme::First,
+                    Some("never") => PrependScheme::Never,
+                    Some(other) => return Err(format!("metaspace prepend scheme `{}` is not supported", other)),
+                    None if flag("add_prefix_space", true) => PrependScheme::Always,
+                    None => PrependScheme::Never,
+                };
+                Ok(PreTokenizer::Metaspace{
+                    replacement: char_field(value, "replacement")?,
+                    prepend_scheme,
+                    split: flag("split", true),
+                })
+            },
+            "ByteLevel" => Ok(PreTokenizer::ByteLevel{
+                add_prefix_space: flag("add_prefix_space", true),
+                pattern: if flag("use_regex", true) {Some(Pattern::regex(BYTE_LEVEL_PATTERN)?)} else {None},
+            }),
+            "Split" => Ok(PreTokenizer::Split{
+                pattern: Pattern::parse(field(value, "pattern")?)?,
+                behavior: Behavior::parse(value.get("behavior"))?,
+                invert: flag("invert", false),
+            }),
+            "Sequence" => array_field(value, "pretokenizers")?
+                .iter()
+                .map(PreTokenizer::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map(PreTokenizer::Sequence),
+            other => Err(format!("pre-tokenizer `{}` is not supported", other)),
+        }
+    }
+
+    /// split each piece, `first` tells whether pieces start at the beginning of text
+    fn split(&self, pieces: Vec<String>, first: bool) -> Vec<String>{
+        if let PreTokenizer::Sequence(pre_tokenizers) = self{
+            return pre_tokenizers
+                .iter()
+                .fold(pieces, |pieces, pre_tokenizer|pre_tokenizer.split(pieces, first));
+        }
+        pieces
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, piece)|self.split_piece(&piece, first && i == 0))
+            .collect()
+    }
+
+    fn split_piece(&self, piece: &str, first: bool) -> Vec<String>{
+        match self{
+            PreTokenizer::Bert => Behavior::Removed
+                .split(piece, &char_matches(piece, char::is_whitespace))
+                .into_iter()
+                .flat_map(|word|Behavior::Isolated.split(&word, &char_matches(&word, is_punctuation)))
+                .collect(),
+            PreTokenizer::Whitespace(pattern) => Behavior::Removed.split_segments(piece, invert(segments(piece, &pattern.find(piece)))),
+            PreTokenizer::WhitespaceSplit => piece.split_whitespace().map(str::to_string).collect(),
+            PreTokenizer::Punctuation(behavior) => behavior.split(piece, &char_matches(piece, is_punctuation)),
+            PreTokenizer::Digits{individual} => {
+                let behavior = if *individual {Behavior::Isolated} else {Behavior::Contiguous};
+                behavior.split(piece, &char_matches(piece, char::is_numeric))
+            },
+            PreTokenizer::CharDelimiterSplit(delimiter) => Behavior::Removed.split(piece, &char_matches(piece, |ch|ch == *delimiter)),
+            PreTokenizer::Metaspace{replacement, prepend_scheme, split} => {
+                let mut piece = piece.replace(' ', &replacement.to_string());
+                let prepend = match prepend_scheme{
+                    PrependScheme::Always => true,
+                    PrependScheme::First => first,
+                    PrependScheme::Never => false,
+                };
+                if prepend && !piece.starts_with(*replacement){
+                    piece.insert(0, *replacement);
+                }
+                if *split{
+                    Behavior::MergedWithNext.split(&piece, &char_matches(&piece, |ch|ch == *replacement))
+                }else {
+                    vec![piece]
+                }
+            },
+            PreTokenizer::ByteLevel{add_prefix_space, pattern} => {
+                let piece = if *add_prefix_space && !piece.starts_with(' ') {format!(" {}", piece)} else {piece.to_string()};
+                let pieces = match pattern{
+                    Some(pattern) => Behavior::Isolated.split(&piece, &pattern.find(&piece)),
+                    None => vec![piece],
+                };
+                pieces.iter().map(|piece|byte_level(piece)).collect()
+            },
+            PreTokenizer::Split{pattern, behavior, invert: inverted} => {
+                let segments = segments(piece, &pattern.find(piece));
+                behavior.split_segments(piece, if *inverted {invert(segments)} else {segments})
+            },
+            PreTokenizer::Sequence(_) => self.split(vec![piece.to_string()], first),
+        }
+    }
+}
+
+/// chars of bytes of GPT-2 byte level, printable bytes are themselves and the others are shifted
+/// after 255
+fn byte_level(piece: &str) -> String{
+    piece
+        .bytes()
+        .map(|byte|{
+            let printable = matches!(byte, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+            if printable{
+                char::from(byte)
+            }else {
+                let shift = (0..byte)
+                    .filter(|b|!matches!(b, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF))
+                    .count();
+                char::from_u32(256 + shift as u32).unwrap()
+            }
+        })
+        .collect()
+}
+
+enum Model{
+    WordPiece{unknown: String, prefix: String, max_chars: usize},
+    Bpe{
+        /// rank and merged token of pair by ids
+        merges: HashMap<(usize, usize), (usize, usize)>,
+        unknown: Option<String>,
+        prefix: Option<String>,
+        suffix: Option<String>,
+        fuse_unk: bool,
+        byte_fallback: bool,
+        ignore_merges: bool,
+    },
+}
+
+impl Model {
+    fn parse(value: &Value, vocab: &HashMap<String, usize>) -> Result<Self, Invalid>{
+        let optional = |name: &str|value.get(name).and_then(Value::as_str).map(str::to_string);
+        let flag = |name: &str|value.get(name).and_then(Value::as_bool).unwrap_or(false);
+        match type_of(value)?{
+            "WordPiece" => Ok(Model::WordPiece{
+                unknown: optional("unk_token").unwrap_or_else(||"[UNK]".to_string()),
+                prefix: optional("continuing_subword_prefix").unwrap_or_else(||"##".to_string()),
+                max_chars: value.get("max_input_chars_per_word").and_then(Value::as_u64).unwrap_or(100) as usize,
+            }),
+            "BPE" => {
+                let prefix = optional("continuing_subword_prefix");
+                let prefix_len = prefix.as_ref().map_or(0, String::len);
+                let mut merges = HashMap::new();
+                for (rank, merge) in array_field(value, "merges")?.iter().enumerate(){
+                    let (a, b) = match merge{
+                        Value::String(merge) => merge.split_once(' ').ok_or_else(||format!("merge `{}` is not a pair", merge))?,
+                        Value::Array(pair) if pair.len() == 2 => match (pair[0].as_str(), pair[1].as_str()){
+                            (Some(a), Some(b)) => (a, b),
+                            _ => return Err(format!("merge {} is not a pair", merge)),
+                        },
+                        _ => return Err(format!("merge {} is not a pair", merge)),
+                    };
+                    let id = |token: &str|vocab
+                        .get(token)
+                        .copied()
+                        .ok_or_else(||format!("token `{}` of merges is not found in vocab", token));
+                    let merged = format!("{}{}", a, b.get(prefix_len..).unwrap_or(""));
+                    merges.insert((id(a)?, id(b)?), (rank, id(&merged)?));
+                }
+                Ok(Model::Bpe{
+                    merges,
+                    unknown: optional("unk_token"),
+                    prefix,
+                    suffix: optional("end_of_word_suffix"),
+                    fuse_unk: flag("fuse_unk"),
+                    byte_fallback: flag("byte_fallback"),
+                    ignore_merges: flag("ignore_merges"),
+                })
+            },
+            other => Err(format!("model `{}` is not supported", other)),
+        }
+    }
+
+    /// ids of word
+    fn tokenize(&self, word: &str, vocab: &HashMap<String, usize>) -> Vec<usize>{
+        match self{
+            Model::WordPiece{unknown, prefix, max_chars} => {
+                let unknown = ||vocab.get(unknown).copied().into_iter().collect();
+                if word.chars().count() > *max_chars{
+                    return unknown();
+                }
+                let mut ids = Vec::new();
+                let mut start = 0;
+                while start < word.len(){
+                    let prefix = if start == 0 {""} else {prefix.as_str()};
+                    let longest = word[start..]
+                        .char_indices()
+                        .map(|(i, ch)|start + i + ch.len_utf8())
+                        .rev()
+                        .find_map(|end|vocab.get(&format!("{}{}", prefix, &word[start..end])).map(|id|(end, *id)));
+                    match longest{
+                        Some((end, id)) => {
+                            ids.push(id);
+                            start = end;
+                        },
+                        None => return unknown(),
+                    }
+                }
+                ids
+            },
+            Model::Bpe{merges, unknown, prefix, suffix, fuse_unk, byte_fallback, ignore_merges} => {
+                if *ignore_merges{
+                    if let Some(id) = vocab.get(word){
+                        return vec![*id];
+                    }
+                }
+                let unk_id = unknown.as_ref().and_then(|unknown|vocab.get(unknown)).copied();
+                let chars = word.chars().count();
+                let mut ids: Vec<usize> = Vec::new();
+                let mut previous_unk = false;
+                for (i, ch) in word.chars().enumerate(){
+                    let mut symbol = ch.to_string();
+                    if i > 0{
+                        if let Some(prefix) = prefix{
+                            symbol.insert_str(0, prefix);
+                        }
+                    }
+                    if i + 1 == chars{
+                        if let Some(suffix) = suffix{
+                            symbol.push_str(suffix);
+                        }
+                    }
+                    if let Some(id) = vocab.get(&symbol){
+                        ids.push(*id);
+                        previous_unk = false;
+                        continue;
+                    }
+                    if *byte_fallback{
+                        let bytes = ch
+                            .encode_utf8(&mut [0; 4])
+                            .bytes()
+                            .map(|byte|vocab.get(&format!("<0x{:02X}>", byte)).copied())
+                            .collect::<Option<Vec<_>>>();
+                        if let Some(bytes) = bytes{
+                            ids.extend(bytes);
+                            previous_unk = false;
+                            continue;
+                        }
+                    }
+                    if let Some(unk_id) = unk_id{
+                        if !(*fuse_unk && previous_unk){
+                            ids.push(unk_id);
+                        }
+                        previous_unk = true;
+                    }
+                }
+                // merge the pair of lowest rank, the leftmost one of the same rank
+                loop{
+                    let lowest = ids
+                        .windows(2)
+                        .enumerate()
+                        .filter_map(|(i, pair)|merges.get(&(pair[0], pair[1])).map(|(rank, merged)|(*rank, i, *merged)))
+                        .min();
+                    match lowest{
+                        Some((_, i, merged)) => {
+                            ids[i] = merged;
+                            ids.remove(i + 1);
+                        },
+                        None => return ids,
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// added token of tokenizer.json, which is matched in raw text before normalization
+struct AddedToken{
+    content: String,
+    id: usize,
+    single_word: bool,
+    lstrip: bool,
+    rstrip: bool,
+}
+
+/// HuggingFace tokenizer of tokenizer.json, with normalizer, pre-tokenizer, WordPiece or BPE model,
+/// added tokens and the special tokens of post-processor
+pub(crate) struct HfTokenizer{
+    normalizer: Option<Normalizer>,
+    pre_tokenizer: Option<PreTokenizer>,
+    model: Model,
+    added_tokens: Vec<AddedToken>,
+    /// vocabulary of model and added tokens
+    vocab: HashMap<String, usize>,
+    tokens: HashMap<usize, String>,
+    framing: Option<Framing>,
+    /// id of padding token
+    pad_id: usize,
+}
+
+impl HfTokenizer {
+    pub fn load(tokenizer_file: &Path) -> Result<Self, DatasetError>{
+        eprintln!("reader tokenizer file from {}", tokenizer_file.display());
+        let reader = BufReader::new(open_file(tokenizer_file)?);
+        let value: Value = serde_json::from_reader(reader)
+            .map_err(|err|DatasetError::content(tokenizer_file, format!("invalid json: {}", err)))?;
+        Self::parse(&value).map_err(|message|DatasetError::content(tokenizer_file, message))
+    }
+
+    fn parse(value: &Value) -> Result<Self, Invalid>{
+        let model = field(value, "model")?;
+        let mut vocab = field(model, "vocab")?
+            .as_object()
+            .ok_or("vocab of model is not an object")?
+            .iter()
+            .map(|(token, id)|id
+                .as_u64()
+                .map(|id|(token.clone(), id as usize))
+                .ok_or_else(||format!("id of token `{}` is not an integer", token)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        let mut added_tokens = Vec::new();
+        for token in value.get("added_tokens").and_then(Value::as_array).into_iter().flatten(){
+            let flag = |name: &str|token.get(name).and_then(Value::as_bool).unwrap_or(false);
+            let added = AddedToken{
+                content: string_field(token, "content")?.to_string(),
+                id: field(token, "id")?.as_u64().ok_or("id of added token is not an integer")? as usize,
+                single_word: flag("single_word"),
+                lstrip: flag("lstrip"),
+                rstrip: flag("rstrip"),
+            };
+            vocab.insert(added.content.clone(), added.id);
+            added_tokens.push(added);
+        }
+        // the longest token is matched first
+        added_tokens.sort_by(|a, b|b.content.len().cmp(&a.content.len()).then_with(||a.content.cmp(&b.content)));
+        let optional = |name: &str|value.get(name).filter(|value|!value.is_null());
+        // pad_id of padding, otherwise the padding token of added tokens, otherwise id 0 as vocab files
+        let pad_id = match optional("padding"){
+            Some(padding) => field(padding, "pad_id")?.as_u64().ok_or("pad_id of padding is not an integer")? as usize,
+            None => added_tokens
+                .iter()
+                .find(|token|matches!(token.content.as_str(), "<pad>" | "[PAD]"))
+                .map_or(0, |token|token.id),
+        };
+        Ok(Self{
+            normalizer: optional("normalizer").map(Normalizer::parse).transpose()?,
+            pre_tokenizer: optional("pre_tokenizer").map(PreTokenizer::parse).transpose()?,
+            model: Model::parse(model, &vocab)?,
+            added_tokens,
+            tokens: vocab.iter().map(|(token, id)|(*id, token.clone())).collect(),
+            vocab,
+            framing: optional("post_processor").map(framing).transpose()?.flatten(),
+            pad_id,
+        })
+    }
+
+    pub fn vocab(&self) -> &HashMap<String, usize>{
+        &self.vocab
+    }
+
+    /// special tokens of post-processor for a single sentence
+    pub fn framing(&self) -> Option<Framing>{
+        self.framing.clone()
+    }
+
+    /// id sequences are padded with
+    pub fn pad_id(&self) -> usize{
+        self.pad_id
+    }
+
+    /// split text into sections of raw text and ids of added tokens
+    fn split_added<'t>(&self, text: &'t str) -> Vec<Result<&'t str, usize>>{
+        let mut sections = Vec::new();
+        let mut start = 0;
+        let mut position = 0;
+        'scan: while position < text.len(){
+            for token in &self.added_tokens{
+                if token.content.is_empty() || !text[position..].starts_with(&token.content){
+                    continue;
+                }
+                let end = position + token.content.len();
+                let is_word = |ch: Option<char>|ch.is_some_and(|ch|ch.is_alphanumeric() || ch == '_');
+                if token.single_word && (is_word(text[..position].chars().next_back()) || is_word(text[end..].chars().next())){
+                    continue;
+                }
+                let before = if token.lstrip {text[start..position].trim_end()} else {&text[start..position]};
+                sections.push(Ok(before));
+                sections.push(Err(token.id));
+                position = if token.rstrip {text.len() - text[end..].trim_start().len()} else {end};
+                start = position;
+                continue 'scan;
+            }
+            position += text[position..].chars().next().unwrap().len_utf8();
+        }
+        sections.push(Ok(&text[start..]));
+        sections
+    }
+}
+
+impl Tokenizer for HfTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        let mut ids = Vec::new();
+        for (i, section) in self.split_added(text).into_iter().enumerate(){
+            let section = match section{
+                Ok("") => continue,
+                Ok(section) => section,
+                Err(id) => {
+                    ids.push(id);
+                    continue;
+                },
+            };
+            let normalized = match &self.normalizer{
+                Some(normalizer) => normalizer.normalize(section.to_string()),
+                None => section.to_string(),
+            };
+            let words = match &self.pre_tokenizer{
+                Some(pre_tokenizer) => pre_tokenizer.split(vec![normalized], i == 0),
+                None => vec![normalized],
+            };
+            words
+                .iter()
+                .filter(|word|!word.is_empty())
+                .for_each(|word|ids.extend(self.model.tokenize(word, &self.vocab)));
+        }
+        ids.into_iter()
+            .map(|id|Cow::Owned(self.tokens[&id].clone()))
+            .collect()
+    }
+}
+
+/// framing of post-processor, TemplateProcessing uses its single template
+fn framing(value: &Value) -> Result<Option<Framing>, Invalid>{
+    let special = |name: &str| -> Result<usize, Invalid>{
+        field(value, name)?
+            .get(1)
+            .and_then(Value::as_u64)
+            .map(|id|id as usize)
+            .ok_or_else(||format!("{} of post-processor is not a pair of token and id", name))
+    };
+    match type_of(value)?{
+        "BertProcessing" | "RobertaProcessing" => Ok(Some(Framing::new(vec![special("cls")?], vec![special("sep")?]))),
+        "ByteLevel" => Ok(None),
+        "TemplateProcessing" => {
+            let special_tokens = value.get("special_tokens");
+            let (mut prefix, mut suffix) = (Vec::new(), Vec::new());
+            let mut sequence = false;
+            for piece in array_field(value, "single")?{
+                if piece.get("Sequence").is_some(){
+                    sequence = true;
+                    continue;
+                }
+                let id = piece
+                    .get("SpecialToken")
+                    .and_then(|token|token.get("id"))
+                    .and_then(Value::as_str)
+                    .ok_or_else(||format!("piece {} of template is not supported", piece))?;
+                let ids = special_tokens
+                    .and_then(|tokens|tokens.get(id))
+                    .and_then(|token|token.get("ids"))
+                    .and_then(Value::as_array)
+                    .ok_or_else(||format!("special token `{}` of template is not found", id))?
+                    .iter()
+                    .map(|id|id.as_u64().map(|id|id as usize).ok_or("id of special token is not an integer"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if sequence {suffix.extend(ids)} else {prefix.extend(ids)}
+            }
+            Ok(Some(Framing::new(prefix, suffix)))
+        },
+        "Sequence" => {
+            let framings = array_field(value, "processors")?
+                .iter()
+                .map(framing)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(framings.into_iter().flatten().last())
+        },
+        other => Err(format!("post-processor `{}` is not supported", other)),
+    }
+}
+
+fn type_of(value: &Value) -> Result<&str, Invalid>{
+    string_field(value, "type")
+}
+
+fn field<'v>(value: &'v Value, name: &str) -> Result<&'v Value, Invalid>{
+    value.get(name).ok_or_else(||format!("field `{}` is not found in {}", name, value))
+}
+
+fn string_field<'v>(value: &'v Value, name: &str) -> Result<&'v str, Invalid>{
+    field(value, name)?.as_str().ok_or_else(||format!("field `{}` is not a string", name))
+}
+
+fn array_field<'v>(value: &'v Value, name: &str) -> Result<&'v Vec<Value>, Invalid>{
+    field(value, name)?.as_array().ok_or_else(||format!("field `{}` is not an array", name))
+}
+
+fn char_field(value: &Value, name: &str) -> Result<char, Invalid>{
+    let text = string_field(value, name)?;
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()){
+        (Some(ch), None) => Ok(ch),
+        _ => Err(format!("field `{}` is not a char", name)),
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+use std::sync::Arc;
+use arrow::array::{ArrayRef, PrimitiveArray};
+use arrow::datatypes::{ArrowNativeType, ArrowPrimitiveType, DataType, Field, UInt8Type, UInt16Type, UInt32Type};
+use clap::ArgEnum;
+use crate::dataset::error::DatasetError;
+use crate::dataset::layout::{sequence_arrays, sequence_fields, Layout};
+
+/// integer type of id column
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdType{
+    /// the narrowest type holds all ids
+    Auto,
+    U8,
+    U16,
+    U32,
+}
+
+impl IdType {
+    /// largest id of the type
+    pub fn max_id(&self) -> usize{
+        match self{
+            IdType::U8 => u8::MAX as usize,
+            IdType::U16 => u16::MAX as usize,
+            IdType::U32 | IdType::Auto => u32::MAX as usize,
+        }
+    }
+
+    /// type of ids in `0..size`, auto picks the narrowest one, none if the ids overflow the type
+    pub fn resolve(self, size: usize) -> Option<IdType>{
+        let max_id = size.saturating_sub(1);
+        match self{
+            IdType::Auto => [IdType::U8, IdType::U16, IdType::U32]
+                .into_iter()
+                .find(|id_type|max_id <= id_type.max_id()),
+            _ => Some(self).filter(|id_type|max_id <= id_type.max_id()),
+        }
+    }
+
+    /// name of resolved type in schema metadata
+    pub fn name(&self) -> &'static str{
+        match self{
+            IdType::U8 => "u8",
+            IdType::U16 => "u16",
+            IdType::U32 => "u32",
+            IdType::Auto => unreachable!("id type must be resolved"),
+        }
+    }
+
+    pub fn data_type(&self) -> DataType{
+        match self{
+            IdType::U8 => DataType::UInt8,
+            IdType::U16 => DataType::UInt16,
+            IdType::U32 => DataType::UInt32,
+            IdType::Auto => unreachable!("id type must be resolved"),
+        }
+    }
+}
+
+/// resolve `kind` type of ids in `0..size` which come from file, error if the ids overflow the type
+pub(crate) fn resolve_id_type(id_type: IdType, size: usize, ids: &str, kind: &str, file: &Path) -> Result<IdType, DatasetError>{
+    id_type
+        .resolve(size)
+        .ok_or_else(||DatasetError::content(file, format!("{} {} overflow {} type {:?}", size, ids, kind, id_type)))
+}
+
+/// field of id column
+pub(crate) fn id_field(id_type: IdType, name: &str) -> Field{
+    Field::new(name, id_type.data_type(), false)
+}
+
+fn primitive_array<T: ArrowPrimitiveType>(ids: &[usize]) -> ArrayRef{
+    let values = ids
+        .iter()
+        .map(|id|T::Native::from_usize(*id)
+            .unwrap_or_else(||panic!("id {} overflows {:?}", id, T::DATA_TYPE)));
+    Arc::new(PrimitiveArray::<T>::from_iter_values(values)) as ArrayRef
+}
+
+/// array of id column
+pub(crate) fn id_array(id_type: IdType, ids: &[usize]) -> ArrayRef{
+    match id_type{
+        IdType::U8 => primitive_array::<UInt8Type>(ids),
+        IdType::U16 => primitive_array::<UInt16Type>(ids),
+        IdType::U32 => primitive_array::<UInt32Type>(ids),
+        IdType::Auto => unreachable!("id type must be resolved"),
+    }
+}
+
+/// fields of id sequence, see `sequence_fields`
+pub(crate) fn id_sequence_fields(id_type: IdType, layout: Layout, prefix: &str, name: &str, max_length: usize) -> Vec<Field>{
+    match id_type{
+        IdType::U8 => sequence_fields::<UInt8Type>(layout, prefix, name, max_length),
+        IdType::U16 => sequence_fields::<UInt16Type>(layout, prefix, name, max_length),
+        IdType::U32 => sequence_fields::<UInt32Type>(layout, prefix, name, max_length),
+        IdType::Auto => unreachable!("id type must be resolved"),
+    }
+}
+
+/// arrays of id sequence, see `sequence_arrays`
+pub(crate) fn id_sequence_arrays(id_type: IdType, layout: Layout, sequences: &[&[usize]], max_length: usize, padding: usize) -> Vec<ArrayRef>{
+    match id_type{
+        IdType::U8 => sequence_arrays::<UInt8Type>(layout, sequences, max_length, padding),
+        IdType::U16 => sequence_arrays::<UInt16Type>(layout, sequences, max_length, padding),
+        IdType::U32 => sequence_arrays::<UInt32Type>(layout, sequences, max_length, padding),
+        IdType::Auto => unreachable!("id type must be resolved"),
+    }
+}
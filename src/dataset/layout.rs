@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use arrow::array::{ArrayRef, FixedSizeListArray, ListArray, PrimitiveArray, UInt32Array};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{ArrowNativeType, ArrowPrimitiveType, DataType, Field, FieldRef, UInt8Type};
+use clap::ArgEnum;
+
+/// layout of sequence in record file
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout{
+    /// one column each position, padded to sequence-length
+    Columns,
+    /// one fixed size list column, padded to sequence-length
+    FixedList,
+    /// one list column without padding
+    List,
+}
+
+/// fields of sequence, `prefix` names the per position columns and `name` names the list column
+pub(crate) fn sequence_fields<T: ArrowPrimitiveType>(layout: Layout, prefix: &str, name: &str, max_length: usize) -> Vec<Field>{
+    let item = item_field::<T>();
+    match layout{
+        Layout::Columns => (0..max_length)
+            .map(|k|Field::new(format!("{}_{}", prefix, k), T::DATA_TYPE, false))
+            .collect(),
+        Layout::FixedList => vec![Field::new(name, DataType::FixedSizeList(item, max_length as i32), false)],
+        Layout::List => vec![Field::new(name, DataType::List(item), false)],
+    }
+}
+
+fn item_field<T: ArrowPrimitiveType>() -> FieldRef{
+    Arc::new(Field::new("item", T::DATA_TYPE, false))
+}
+
+/// arrays of sequence for a chunk of records, padded with padding except the list layout
+pub(crate) fn sequence_arrays<T: ArrowPrimitiveType>(layout: Layout, sequences: &[&[usize]], max_length: usize, padding: usize) -> Vec<ArrayRef>{
+    let native = |id: usize|T::Native::from_usize(id)
+        .unwrap_or_else(||panic!("id {} overflows {:?}", id, T::DATA_TYPE));
+    let padded = |sequence: &[usize], k: usize|native(sequence.get(k).copied().unwrap_or(padding));
+    match layout{
+        Layout::Columns => (0..max_length)
+            .map(|k|{
+                let series = sequences
+                    .iter()
+                    .map(|sequence|padded(sequence, k))
+                    .collect::<Vec<_>>();
+                Arc::new(PrimitiveArray::<T>::from_iter_values(series)) as ArrayRef
+            })
+            .collect(),
+        Layout::FixedList => {
+            let values = sequences
+                .iter()
+                .flat_map(|sequence|(0..max_length).map(|k|padded(sequence, k)))
+                .collect::<Vec<_>>();
+            let values = Arc::new(PrimitiveArray::<T>::from_iter_values(values));
+            let list = FixedSizeListArray::new(item_field::<T>(), max_length as i32, values, None);
+            vec![Arc::new(list) as ArrayRef]
+        },
+        Layout::List => {
+            let offsets = OffsetBuffer::from_lengths(sequences.iter().map(|sequence|sequence.len()));
+            let values = sequences
+                .iter()
+                .flat_map(|sequence|sequence.iter().map(|id|native(*id)))
+                .collect::<Vec<_>>();
+            let values = Arc::new(PrimitiveArray::<T>::from_iter_values(values));
+            let list = ListArray::new(item_field::<T>(), offsets, values, None);
+            vec![Arc::new(list) as ArrayRef]
+        },
+    }
+}
+
+/// length column of sequence before padding
+pub(crate) fn length_array(sequences: &[&[usize]]) -> ArrayRef{
+    let lengths = sequences
+        .iter()
+        .map(|sequence|sequence.len() as u32)
+        .collect::<Vec<u32>>();
+    Arc::new(UInt32Array::from(lengths)) as ArrayRef
+}
+
+/// attention mask of sequence, 1 for token and 0 for padding
+pub(crate) fn mask_arrays(layout: Layout, sequences: &[&[usize]], max_length: usize) -> Vec<ArrayRef>{
+    let masks = sequences
+        .iter()
+        .map(|sequence|vec![1usize; sequence.len()])
+        .collect::<Vec<_>>();
+    let masks = masks.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    sequence_arrays::<UInt8Type>(layout, &masks, max_length, 0)
+}
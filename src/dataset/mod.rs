@@ -1,7 +1,17 @@
+mod bpe;
 mod classifier;
+mod error;
+mod hf_tokenizer;
+mod id_type;
+mod layout;
+mod overflow;
 mod similarity;
 mod tagging;
+mod tokenizer;
 mod traits;
+mod truncation;
+mod vocab;
+mod writer;
 
 pub use classifier::{ClassifierArgs, ClassifierBuilder};
 pub use similarity::{SimilarityArgs, SimilarityBuilder};
@@ -0,0 +1,44 @@
+use std::ops::Range;
+use clap::ArgEnum;
+use crate::dataset::error::DatasetError;
+
+/// strategy for sentence longer than sequence-length
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow{
+    /// truncate the sentence by truncation strategy
+    Truncate,
+    /// drop the sentence
+    Drop,
+    /// split the sentence into windows of sequence-length by stride
+    Split,
+}
+
+/// stride of split overflow, default to max_length
+pub(crate) fn window_stride(stride: Option<usize>, max_length: usize) -> usize{
+    stride.unwrap_or(max_length)
+}
+
+/// check stride of split overflow before any record is built, other overflows never use it
+pub(crate) fn check_stride(overflow: Overflow, stride: Option<usize>, max_length: usize) -> Result<(), DatasetError>{
+    let stride = window_stride(stride, max_length);
+    if overflow == Overflow::Split && (stride == 0 || stride > max_length){
+        return Err(DatasetError::arg("stride", format!("stride must be in 1..={}, but got {}", max_length, stride)));
+    }
+    Ok(())
+}
+
+/// ranges of windows no longer than max_length, each window starts stride after the previous one
+/// and the last window ends at the end of sequence
+pub(crate) fn windows(length: usize, max_length: usize, stride: usize) -> Vec<Range<usize>>{
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = length.min(start + max_length);
+        ranges.push(start..end);
+        if end == length{
+            break;
+        }
+        start += stride;
+    }
+    ranges
+}
@@ -1,16 +1,22 @@
-use std::collections::{HashSet, HashMap};
-use std::fs::File;
-use std::io::{BufRead, Write, BufReader, BufWriter};
-use std::path::Path;
-use arrow::ipc::writer::FileWriter;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use arrow::array::{ArrayRef, UInt8Array, UInt32Array};
-use arrow::datatypes::{Schema, Field, DataType};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use arrow::array::{ArrayRef, Float32Array, UInt8Array, UInt64Array};
+use arrow::datatypes::{Schema, SchemaRef, Field, DataType, UInt8Type};
 use arrow::record_batch::{RecordBatch};
 use rayon::prelude::*;
-use indicatif::ProgressBar;
-use crate::dataset::traits::IDataset;
-use clap::Args;
+use crate::dataset::id_type::{id_sequence_arrays, id_sequence_fields, IdType};
+use crate::dataset::layout::{length_array, mask_arrays, sequence_fields, Layout};
+use crate::dataset::error::{column_after, DatasetError, InvalidSample, OnError};
+use crate::dataset::bpe::{save_merges, Merges};
+use crate::dataset::tokenizer::{check_pair_length, check_sequence_length, build_tokenizer, tokenization, tokenizer_type, Framing, Tokenizer, TokenizerOptions, TokenizerType};
+use crate::dataset::traits::{read_lines, IDataset, RawSamples};
+use crate::dataset::truncation::{Truncation, Truncator};
+use crate::dataset::vocab::{save_vocab, VocabLimits};
+use crate::dataset::writer::{Compression, Format, WriterOptions};
+use clap::{ArgAction, ArgEnum, Args};
+use regex::Regex;
 
 /// similarity args structure
 #[derive(Args, Debug)]
@@ -24,19 +30,100 @@ pub struct SimilarityArgs{
     /// with user vocabulary for classifier dataset
     #[clap(long)]
     with_vocab: bool,
-    /// max vocabulary size for build record, only effective when the with-vocab is not set
+    /// vocabulary file for build record, only effective when the with-vocab is set or the tokenizer is wordpiece, default to vocab.txt of dataset path
+    #[clap(long, visible_alias="vocab")]
+    vocab_file: Option<String>,
+    /// max vocabulary size including special tokens, keep the most frequent tokens of train dataset, only effective when the with-vocab is not set
     #[clap(long, default_value = "10000")]
     max_vocab_size: usize,
+    /// min frequency of token for build vocabulary, only effective when the with-vocab is not set
+    #[clap(long, default_value = "1")]
+    min_freq: usize,
+    /// max document frequency ratio of token in (0, 1] for build vocabulary, only effective when the with-vocab is not set
+    #[clap(long, default_value = "1.0")]
+    max_df: f64,
+    /// integer type of token id columns, auto picks the narrowest type holds the vocabulary
+    #[clap(long, arg_enum, default_value = "auto")]
+    token_type: IdType,
     /// max sequence length for sentence
     #[clap(long, default_value = "32")]
     sequence_length: usize,
+    /// strategy of which part of sentence to keep when truncating
+    #[clap(long, arg_enum, default_value = "head")]
+    truncation: Truncation,
+    /// head tokens to keep of head-tail truncation, default to half of the length left for sentence after special tokens
+    #[clap(long)]
+    head_length: Option<usize>,
+    /// format of record file
+    #[clap(long, arg_enum, default_value = "ipc")]
+    format: Format,
+    /// compression of record file
+    #[clap(long, arg_enum)]
+    compression: Option<Compression>,
+    /// max rows of row group, only effective for parquet format
+    #[clap(long)]
+    row_group_size: Option<usize>,
+    /// write records to stdout one split after another, only supported by ipc-stream format
+    #[clap(long)]
+    stdout: bool,
+    /// samples read, encoded and written at a time, peak memory grows with it
+    #[clap(long, default_value = "100000")]
+    chunk_size: usize,
+    /// policy for invalid samples, skip and log write them to <split>.rejected.txt of output path, log also reports each error
+    #[clap(long, arg_enum, default_value = "fail")]
+    on_error: OnError,
+    /// layout of sequence columns in record file
+    #[clap(long, arg_enum, default_value = "columns")]
+    layout: Layout,
+    /// write line_no column of the source line (starts from 1) of each record
+    #[clap(long)]
+    with_line_no: bool,
+    /// write length column of sequence before padding
+    #[clap(long)]
+    with_length: bool,
+    /// write attention mask column of sequence
+    #[clap(long)]
+    with_attention_mask: bool,
+    /// combined length budget of text_a and text_b including special tokens, truncate the longer one first
+    #[clap(long)]
+    pair_length: Option<usize>,
     /// stopwords file for build vocabulary, only effective when the with-vocab is not set
     #[clap(long, visible_alias="stopwords")]
     stopwords_file: Option<String>,
     /// similarity with boolean value
     #[clap(long)]
     with_bool: bool,
-    /// with en language
+    /// type of label column, int writes u8 labels and float writes f32 scores for regression
+    #[clap(long, arg_enum, default_value = "int")]
+    label_type: LabelType,
+    /// min-max normalize float labels to [0,1] by the label range of train dataset, only effective when the label-type is float
+    #[clap(long)]
+    normalize_label: bool,
+    /// tokenizer of sentence
+    #[clap(long, arg_enum, default_value = "char")]
+    tokenizer: TokenizerType,
+    /// pattern of tokens for regex tokenizer, default to words and single punctuations
+    #[clap(long)]
+    token_pattern: Option<Regex>,
+    /// tokenizer.json of HuggingFace tokenizers, overrides the tokenizer, vocabulary and special tokens
+    #[clap(long)]
+    tokenizer_file: Option<String>,
+    /// classification token starts each sentence of wordpiece tokenizer
+    #[clap(long, default_value = "[CLS]")]
+    cls_token: String,
+    /// separator token ends each sentence of wordpiece tokenizer
+    #[clap(long, default_value = "[SEP]")]
+    sep_token: String,
+    /// lowercase text of wordpiece tokenizer as uncased BERT models, false for cased ones
+    #[clap(long, default_value = "true", action = ArgAction::Set)]
+    lowercase: bool,
+    /// strip accents of text of wordpiece tokenizer, default to lowercase
+    #[clap(long, action = ArgAction::Set)]
+    strip_accents: Option<bool>,
+    /// merges file of bpe tokenizer, only effective when the with-vocab is set, default to merges.txt of dataset path
+    #[clap(long, visible_alias="merges")]
+    merges_file: Option<String>,
+    /// with en language, same as the whitespace tokenizer
     #[clap(long)]
     with_lang_en: bool,
     /// separator between text_a and text_b
@@ -45,52 +132,65 @@ pub struct SimilarityArgs{
     #[clap(long, visible_alias = "s2", default_value = "\t")]
     /// separator between text and label
     label_sep: String,
-    #[clap(long, visible_alias = "unk-token", default_value = "<UNK>")]
+    /// unknown special token of vocabulary, default to [UNK] for wordpiece tokenizer
+    #[clap(long, visible_alias = "unk-token", default_value = "<UNK>", default_value_if("tokenizer", Some("wordpiece"), Some("[UNK]")))]
     unknown: String,
-    /// padding special token of vocabulary
-    #[clap(long, visible_alias = "pad-token", default_value = "<PAD>")]
+    /// padding special token of vocabulary, default to [PAD] for wordpiece tokenizer
+    #[clap(long, visible_alias = "pad-token", default_value = "<PAD>", default_value_if("tokenizer", Some("wordpiece"), Some("[PAD]")))]
     padding: String,
 }
 
+/// type of similarity label
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelType{
+    /// integer of 0..=255, or a bool with with-bool
+    Int,
+    /// float score such as 3.8 of STS-B
+    Float,
+}
+
+/// label is kept as f32 for both label types, integer labels of 0..=255 are exact in f32
 pub(crate) struct  SimilarityRecord{
     front_word_ids: Vec<usize>,
     back_word_ids: Vec<usize>,
-    label: u8
+    label: f32,
+    line_no: usize,
 }
 
 impl SimilarityRecord {
-    pub fn new(mut front_word_ids: Vec<usize>, mut back_word_ids: Vec<usize>, label: u8, max_length: usize) -> Self{
-        let front_length = front_word_ids.len();
-        let back_length = back_word_ids.len();
-        if front_length > max_length{
-            let _ = front_word_ids.split_off(max_length);
-        }else if front_length < max_length{
-            front_word_ids.append(&mut vec![0usize; max_length - front_length]);
-        }
-        if back_length > max_length{
-            let _ = back_word_ids.split_off(max_length);
-        }else if back_length < max_length {
-            back_word_ids.append(&mut vec![0usize; max_length - back_length]);
-        }
+    pub fn new(front_word_ids: Vec<usize>, back_word_ids: Vec<usize>, label: f32, line_no: usize) -> Self{
         Self{
             front_word_ids,
             back_word_ids,
-            label
+            label,
+            line_no,
         }
     }
 }
-pub(crate) struct  SimilaritySample(String, String, u8);
+pub(crate) struct  SimilaritySample(String, String, f32, usize);
 
 impl SimilaritySample{
-    pub fn new(sent_a: & str, sent_b: & str, label: u8) -> Self{
-        Self(sent_a.to_string(), sent_b.to_string(), label)
+    pub fn new(sent_a: & str, sent_b: & str, label: f32, line_no: usize) -> Self{
+        Self(sent_a.to_string(), sent_b.to_string(), label, line_no)
     }
 }
 
 pub struct SimilarityBuilder<'a>{
     args: &'a SimilarityArgs,
     vocab: HashMap<String, usize>,
-    stopwords: HashSet<String>,
+    tokenizer: Box<dyn Tokenizer>,
+    /// `[CLS]` and `[SEP]` of wordpiece tokenizer
+    framing: Option<Framing>,
+    /// merges of bpe tokenizer
+    merges: Merges,
+    /// id of padding token, 0 except tokenizer file
+    pad_id: usize,
+    /// vocab and merges files given by user, kept as they are when saving vocabulary
+    user_files: Vec<PathBuf>,
+    token_type: IdType,
+    label_range: Option<(f32, f32)>,
+    /// normalized labels of dev and test out of [0,1], clamped into it
+    clamped: AtomicUsize,
 }
 
 impl<'a> SimilarityBuilder<'a> {
@@ -98,177 +198,245 @@ impl<'a> SimilarityBuilder<'a> {
         Self{
             args,
             vocab: HashMap::new(),
-            stopwords: HashSet::new(),
+            tokenizer: build_tokenizer(tokenizer_type(args.tokenizer, args.with_lang_en), args.token_pattern.as_ref()),
+            framing: None,
+            merges: Vec::new(),
+            pad_id: 0,
+            user_files: Vec::new(),
+            token_type: args.token_type,
+            label_range: None,
+            clamped: AtomicUsize::new(0),
+        }
+    }
+    fn tokenizer_options(&self) -> TokenizerOptions<'_>{
+        TokenizerOptions{
+            tokenizer: Some(tokenizer_type(self.args.tokenizer, self.args.with_lang_en)),
+            token_pattern: self.args.token_pattern.as_ref(),
+            tokenizer_file: self.args.tokenizer_file.as_deref(),
+            cls_token: &self.args.cls_token,
+            sep_token: &self.args.sep_token,
+            lowercase: self.args.lowercase,
+            strip_accents: self.args.strip_accents,
+            path: self.get_input_path(),
+            with_vocab: self.args.with_vocab,
+            vocab_file: self.args.vocab_file.as_deref(),
+            merges_file: self.args.merges_file.as_deref(),
+            stopwords_file: self.args.stopwords_file.as_deref(),
+            padding: &self.args.padding,
+            unknown: &self.args.unknown,
+            limits: VocabLimits{
+                max_vocab_size: self.args.max_vocab_size,
+                min_freq: self.args.min_freq,
+                max_df: self.args.max_df,
+            },
+            token_type: self.args.token_type,
+        }
+    }
+    /// min and max label of train dataset for normalization
+    fn count_label_range(&self) -> Result<(f32, f32), DatasetError>{
+        let mut range = (f32::INFINITY, f32::NEG_INFINITY);
+        for samples in self.read_chunks("train.txt", |_, _|Ok(()))?{
+            range = samples?
+                .iter()
+                .fold(range, |(min, max), sample|(min.min(sample.2), max.max(sample.2)));
+        }
+        if range.0 > range.1{
+            let message = "train dataset has no label for normalization".to_string();
+            return Err(DatasetError::content(&self.get_input_path().join("train.txt"), message));
         }
+        eprintln!("normalize labels by range [{}, {}] of train dataset", range.0, range.1);
+        Ok(range)
+    }
+    /// min-max normalized label, all labels are 0 if train dataset has a single label value,
+    /// labels out of the range of train dataset are clamped to [0,1]
+    fn normalize(&self, label: f32) -> f32{
+        let normalized = match self.label_range{
+            Some((min, max)) if max > min => (label - min) / (max - min),
+            Some(_) => 0.0,
+            None => return label
+        };
+        if !(0.0..=1.0).contains(&normalized){
+            self.clamped.fetch_add(1, Ordering::Relaxed);
+        }
+        normalized.clamp(0.0, 1.0)
     }
 }
 
 impl<'a> IDataset<SimilaritySample, SimilarityRecord> for SimilarityBuilder<'a>  {
-    fn init(&mut self, train_samples: & Vec<SimilaritySample>){
-        if let Some(stopwords_file) = &self.args.stopwords_file{
-            println!("reader stopwords file from {}", stopwords_file);
-            let stopwords_reader = BufReader::new(File::open(stopwords_file).expect("open stopwords file failed"));
-            stopwords_reader
-                .lines()
-                .filter_map(Result::ok)
-                .for_each(|word|{
-                    self.stopwords.insert(word);
-                })
+    fn init(&mut self) -> Result<(), DatasetError>{
+        if self.args.label_type == LabelType::Float && self.args.normalize_label{
+            self.label_range = Some(self.count_label_range()?);
         }
-        self.vocab.insert(self.args.padding.to_owned(), 0);
-        let mut vocab = HashSet::new();
-        train_samples
-            .iter()
-            .for_each(|sample|if self.args.with_lang_en{
-                sample.0
-                    .split(' ')
-                    .for_each(|word|{vocab.insert(word.to_string());});
-                sample.1
-                    .split(' ')
-                    .for_each(|word|{vocab.insert(word.to_string());});
-            }else {
-                sample.0
-                    .chars()
-                    .for_each(|ch|{vocab.insert(ch.to_string());});
-                sample.1
-                    .chars()
-                    .for_each(|ch|{vocab.insert(ch.to_string());});
-            });
-        vocab
-            .into_iter()
-            .filter(|word|!self.stopwords.contains(word))
-            .enumerate()
-            .for_each(|(i, word)|{self.vocab.insert(word, i + 1);});
-        let len = self.vocab.len();
-        self.vocab.insert(self.args.unknown.to_owned(), len);
+        let tokenization = tokenization(&self.tokenizer_options(), |tokenizer, add|{
+            for samples in self.read_chunks("train.txt", |_, _|Ok(()))?{
+                samples?
+                    .iter()
+                    .for_each(|sample|{
+                        let mut tokens = tokenizer.tokenize(&sample.0);
+                        tokens.extend(tokenizer.tokenize(&sample.1));
+                        add(&tokens);
+                    });
+            }
+            Ok(())
+        })?;
+        self.tokenizer = tokenization.tokenizer;
+        self.vocab = tokenization.vocab;
+        self.framing = tokenization.framing;
+        self.merges = tokenization.merges;
+        self.pad_id = tokenization.pad_id;
+        self.user_files = tokenization.user_files;
+        self.token_type = tokenization.token_type;
+        check_sequence_length(self.framing.as_ref(), self.args.sequence_length)?;
+        check_pair_length(self.framing.as_ref(), self.args.pair_length)
     }
 
-    fn read_dataset(&self, file: & str) -> Vec<SimilaritySample>{
-        let base_path = Path::new(&self.args.path);
-        let data_file = base_path.join(file);
-        let data_reader = BufReader::new(File::open(data_file).unwrap());
-        data_reader
-            .lines()
-            .par_bridge()
-            .filter_map(Result::ok)
-            .map(|line| line
-                .split_once(&self.args.label_sep)
-                .map(|(context, label)|(context.to_string(), label.to_string()))
-                .map(|(context, label)|{
-                    context
-                        .split_once(&self.args.sent_sep)
-                        .map(|item|{
-                            let  label_id;
-                            if self.args.with_bool{
-                                let tag: bool = label.parse().unwrap();
-                                label_id = tag as u8;
-                            }else {
-                                label_id = label.parse().unwrap();
-                            }
-                            SimilaritySample::new(item.0, item.1, label_id)
-                        }).unwrap()
-                }).unwrap()
-            )
-            .collect()
+    fn read_dataset(&self, file: &Path) -> Result<RawSamples<'_>, DatasetError>{
+        read_lines(file)
+    }
+    fn parse_sample(&self, _index: usize, line_no: usize, raw: &str) -> Result<SimilaritySample, InvalidSample>{
+        let (context, label) = raw
+            .rsplit_once(&self.args.label_sep)
+            .ok_or_else(||InvalidSample::new(line_no, column_after(raw), format!("label separator {:?} is not found", self.args.label_sep)))?;
+        let (text_a, text_b) = context
+            .split_once(&self.args.sent_sep)
+            .ok_or_else(||InvalidSample::new(line_no, column_after(context), format!("sentence separator {:?} is not found", self.args.sent_sep)))?;
+        let column = column_after(&raw[..raw.len() - label.len()]);
+        let label_id = if self.args.label_type == LabelType::Float{
+            label
+                .parse::<f32>()
+                .ok()
+                .filter(|score|score.is_finite())
+                .ok_or_else(||InvalidSample::new(line_no, column, format!("label `{}` is not a finite float", label)))?
+        }else if self.args.with_bool{
+            let tag: bool = label
+                .parse()
+                .map_err(|_|InvalidSample::new(line_no, column, format!("label `{}` is not a bool", label)))?;
+            tag as u8 as f32
+        }else {
+            label
+                .parse::<u8>()
+                .map_err(|_|InvalidSample::new(line_no, column, format!("label `{}` is not an integer of 0..=255", label)))? as f32
+        };
+        Ok(SimilaritySample::new(text_a, text_b, label_id, line_no))
     }
     fn build_dataset(&self, samples: Vec<SimilaritySample>) -> Vec<SimilarityRecord>{
-        let max_length = self.args.sequence_length;
-        let unk_id = self.vocab.get(&self.args.unknown).unwrap();
-        let pb = ProgressBar::new(samples.len() as u64);
-        let records = samples
+        // each text is framed after truncation
+        let framing_length = self.framing.as_ref().map_or(0, Framing::length);
+        let max_length = self.args.sequence_length.saturating_sub(framing_length);
+        let truncator = Truncator::new(self.args.truncation, self.args.head_length);
+        // vocabulary of tokenizer file may have no unknown token, tokens out of it are dropped
+        let unk_id = self.vocab.get(&self.args.unknown).copied();
+        samples
             .into_par_iter()
             .map(|sample|{
-                pb.inc(1);
-                if self.args.with_lang_en{
-                    let text_a_ids = sample.0
-                        .split(' ')
-                        .map(|word|self.vocab
-                            .get(word)
-                            .map(|it|*it)
-                            .unwrap_or(*unk_id)
-                        ).collect::<Vec<_>>();
-                    let text_b_ids = sample.1
-                        .split(' ')
-                        .map(|word|self.vocab
-                            .get(word)
-                            .map(|it|*it)
-                            .unwrap_or(*unk_id)
-                        ).collect::<Vec<_>>();
-                    (text_a_ids, text_b_ids, sample.2)
-                }else {
-                    let text_a_ids = sample.0
-                        .chars()
-                        .map(|ch|self.vocab
-                            .get(&ch.to_string())
-                            .map(|it|*it)
-                            .unwrap_or(*unk_id)
-                        ).collect::<Vec<_>>();
-                    let text_b_ids = sample.1
-                        .chars()
-                        .map(|ch|self.vocab
-                            .get(&ch.to_string())
-                            .map(|it|*it)
-                            .unwrap_or(*unk_id)
-                        ).collect::<Vec<_>>();
-                    (text_a_ids, text_b_ids, sample.2)
-                }
+                let word_ids = |text: &str|self.tokenizer
+                    .tokenize(text)
+                    .iter()
+                    .filter_map(|token|self.vocab
+                        .get(token.as_ref())
+                        .copied()
+                        .or(unk_id)
+                    ).collect::<Vec<_>>();
+                (word_ids(&sample.0), word_ids(&sample.1), sample.2, sample.3)
             })
-            .map(|(text_a_ids, text_b_ids, label)|{
-                SimilarityRecord::new(text_a_ids, text_b_ids, label, max_length)
-            }).collect();
-        pb.finish_with_message("done");
-        records
+            .map(|(mut text_a_ids, mut text_b_ids, label, line_no)|{
+                if let Some(pair_length) = self.args.pair_length{
+                    truncator.truncate_pair(&mut text_a_ids, &mut text_b_ids, pair_length.saturating_sub(2 * framing_length));
+                }
+                truncator.truncate(&mut text_a_ids, max_length);
+                truncator.truncate(&mut text_b_ids, max_length);
+                if let Some(framing) = &self.framing{
+                    framing.frame(&mut text_a_ids);
+                    framing.frame(&mut text_b_ids);
+                }
+                SimilarityRecord::new(text_a_ids, text_b_ids, self.normalize(label), line_no)
+            }).collect()
     }
-    fn save_vocab(&self){
-        let output_path = self.get_output_path();
-        let vocab_file = File::create(output_path.join("vocab.txt")).expect("create vocab file failed");
-        let mut writer = BufWriter::new(vocab_file);
-        for (word, idx) in &self.vocab{
-            writeln!(&mut writer, "{}\t{}", idx, word).expect("write vocab line failed");
+    fn save_vocab(&self) -> Result<(), DatasetError>{
+        save_vocab(&self.vocab, &self.get_output_path().join("vocab.txt"), &self.user_files)?;
+        if !self.merges.is_empty(){
+            save_merges(&self.merges, &self.get_output_path().join("merges.txt"), &self.user_files)?;
         }
+        Ok(())
     }
-    fn save_dataset(&self, records: Vec<SimilarityRecord>, record_file: & str){
-        let output_path = self.get_output_path();
+    fn schema(&self) -> SchemaRef{
         let max_length = self.args.sequence_length;
-        let mut fields = Vec::new();
-        for k in 0..max_length{
-            let field = Field::new(&format!("text_a_{}", k), DataType::UInt32, false);
-            fields.push(field);
+        let layout = self.args.layout;
+        let mut fields = id_sequence_fields(self.token_type, layout, "text_a", "text_a_ids", max_length);
+        fields.append(&mut id_sequence_fields(self.token_type, layout, "text_b", "text_b_ids", max_length));
+        let field = match self.args.label_type{
+            LabelType::Int => Field::new("label", DataType::UInt8, false),
+            LabelType::Float => Field::new("label", DataType::Float32, false),
+        };
+        fields.push(field);
+        if self.args.with_line_no{
+            fields.push(Field::new("line_no", DataType::UInt64, false));
         }
-        for k in 0..max_length{
-            let field = Field::new(&format!("text_b_{}", k), DataType::UInt32, false);
-            fields.push(field);
+        if self.args.with_length{
+            fields.push(Field::new("text_a_length", DataType::UInt32, false));
+            fields.push(Field::new("text_b_length", DataType::UInt32, false));
         }
-        let field = Field::new("label", DataType::UInt8, false);
-        fields.push(field);
-        let schema = Arc::new(Schema::new(fields));
-        let record_file = File::create(output_path.join(record_file)).expect(&format!("create record file {} failed", record_file));
-        let mut writer = FileWriter::try_new(record_file, &schema).expect("create file writer failed");
-        for chunk in records.chunks(100){
-            let mut values = Vec::new();
-            for i in 0..max_length{
-                let series_a = chunk
-                    .iter()
-                    .map(|item|item.front_word_ids[i] as u32)
-                    .collect::<Vec<u32>>();
-                values.push(Arc::new(UInt32Array::from(series_a)) as ArrayRef);
-            }
-            for i in 0..max_length{
-                let series_b = chunk
-                    .iter()
-                    .map(|item|item.back_word_ids[i] as u32)
-                    .collect::<Vec<u32>>();
-                values.push(Arc::new(UInt32Array::from(series_b)) as ArrayRef);
-            }
-            let label_ids = chunk
+        if self.args.with_attention_mask{
+            fields.append(&mut sequence_fields::<UInt8Type>(layout, "text_a_mask", "text_a_attention_mask", max_length));
+            fields.append(&mut sequence_fields::<UInt8Type>(layout, "text_b_mask", "text_b_attention_mask", max_length));
+        }
+        let metadata = HashMap::from([("token_type".to_string(), self.token_type.name().to_string())]);
+        Arc::new(Schema::new(fields).with_metadata(metadata))
+    }
+    fn record_batch(&self, schema: SchemaRef, records: &[SimilarityRecord]) -> RecordBatch{
+        let max_length = self.args.sequence_length;
+        let layout = self.args.layout;
+        let text_a_ids = records
+            .iter()
+            .map(|item|item.front_word_ids.as_slice())
+            .collect::<Vec<_>>();
+        let mut values = id_sequence_arrays(self.token_type, layout, &text_a_ids, max_length, self.pad_id);
+        let text_b_ids = records
+            .iter()
+            .map(|item|item.back_word_ids.as_slice())
+            .collect::<Vec<_>>();
+        values.append(&mut id_sequence_arrays(self.token_type, layout, &text_b_ids, max_length, self.pad_id));
+        let labels = records
+            .iter()
+            .map(|item|item.label);
+        let labels = match self.args.label_type{
+            LabelType::Int => Arc::new(labels.map(|label|label as u8).collect::<UInt8Array>()) as ArrayRef,
+            LabelType::Float => Arc::new(labels.collect::<Float32Array>()) as ArrayRef,
+        };
+        values.push(labels);
+        if self.args.with_line_no{
+            let line_nos = records
                 .iter()
-                .map(|item|item.label)
-                .collect::<Vec<u8>>();
-            values.push(Arc::new(UInt8Array::from(label_ids)) as ArrayRef);
-            let batch = RecordBatch::try_new(schema.clone(), values).expect("build batch error");
-            writer.write(&batch).expect("write record error");
+                .map(|item|item.line_no as u64)
+                .collect::<Vec<u64>>();
+            values.push(Arc::new(UInt64Array::from(line_nos)) as ArrayRef);
         }
-        writer.finish().expect("finished write records error");
+        if self.args.with_length{
+            values.push(length_array(&text_a_ids));
+            values.push(length_array(&text_b_ids));
+        }
+        if self.args.with_attention_mask{
+            values.append(&mut mask_arrays(layout, &text_a_ids, max_length));
+            values.append(&mut mask_arrays(layout, &text_b_ids, max_length));
+        }
+        RecordBatch::try_new(schema, values).expect("build batch error")
+    }
+    fn writer_options(&self) -> WriterOptions{
+        WriterOptions{
+            format: self.args.format,
+            compression: self.args.compression,
+            row_group_size: self.args.row_group_size,
+            stdout: self.args.stdout,
+        }
+    }
+    fn chunk_size(&self) -> usize{
+        self.args.chunk_size
+    }
+    fn on_error(&self) -> OnError{
+        self.args.on_error
+    }
+    fn get_input_path(&self) -> &Path{
+        Path::new(&self.args.path)
     }
     fn get_output_path(&self) -> &Path {
         match &self.args.output_path{
@@ -276,4 +444,10 @@ impl<'a> IDataset<SimilaritySample, SimilarityRecord> for SimilarityBuilder<'a>
             Some(output_path) => Path::new(output_path)
         }
     }
+    fn report(&self, split: & str){
+        let clamped = self.clamped.swap(0, Ordering::Relaxed);
+        if clamped > 0{
+            eprintln!("{} labels of {} are out of the range of train dataset, clamped to [0,1]", clamped, split);
+        }
+    }
 }
\ No newline at end of file
@@ -1,16 +1,24 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, Write, BufReader, BufWriter};
-use std::path::Path;
-use arrow::ipc::writer::FileWriter;
+use std::collections::{BTreeSet, HashMap};
+use std::iter;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use arrow::array::{ArrayRef, UInt8Array, UInt32Array};
-use arrow::datatypes::{Schema, Field, DataType};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use arrow::array::{ArrayRef, UInt64Array};
+use arrow::datatypes::{Schema, SchemaRef, Field, DataType, UInt8Type, UInt32Type};
 use arrow::record_batch::{RecordBatch};
 use rayon::prelude::*;
-use clap::Args;
-use crate::dataset::traits::IDataset;
-use indicatif::ProgressBar;
+use clap::{ArgAction, ArgEnum, Args};
+use regex::Regex;
+use crate::dataset::bpe::{save_merges, Merges};
+use crate::dataset::layout::{length_array, mask_arrays, sequence_arrays, sequence_fields, Layout};
+use crate::dataset::overflow::{check_stride, window_stride, windows, Overflow};
+use crate::dataset::id_type::{id_sequence_arrays, id_sequence_fields, resolve_id_type, IdType};
+use crate::dataset::error::{column_after, DatasetError, InvalidSample, OnError};
+use crate::dataset::tokenizer::{check_sequence_length, tokenization, Framing, Tokenizer, TokenizerOptions, TokenizerType, WordTokenizer};
+use crate::dataset::traits::{read_lines, IDataset, RawSamples};
+use crate::dataset::truncation::{Truncation, Truncator};
+use crate::dataset::vocab::{save_lines, save_vocab, VocabLimits};
+use crate::dataset::writer::{Compression, Format, WriterOptions};
 
 /// tagging args structure
 #[derive(Args, Debug)]
@@ -24,71 +32,206 @@ pub struct TaggingArgs{
     /// with user vocabulary for classifier dataset
     #[clap(long)]
     with_vocab: bool,
-    /// max vocabulary size for build record, only effective when the with-vocab is not set
+    /// vocabulary file for build record, only effective when the with-vocab is set or the tokenizer is wordpiece, default to vocab.txt of dataset path
+    #[clap(long, visible_alias="vocab")]
+    vocab_file: Option<String>,
+    /// max vocabulary size including special tokens, keep the most frequent tokens of train dataset, only effective when the with-vocab is not set
     #[clap(long, default_value = "10000")]
     max_vocab_size: usize,
+    /// min frequency of token for build vocabulary, only effective when the with-vocab is not set
+    #[clap(long, default_value = "1")]
+    min_freq: usize,
+    /// max document frequency ratio of token in (0, 1] for build vocabulary, only effective when the with-vocab is not set
+    #[clap(long, default_value = "1.0")]
+    max_df: f64,
+    /// integer type of token id columns, auto picks the narrowest type holds the vocabulary
+    #[clap(long, arg_enum, default_value = "auto")]
+    token_type: IdType,
     /// max sequence length for sentence
     #[clap(long, default_value = "32")]
     sequence_length: usize,
+    /// strategy of which part of sentence to keep when truncating
+    #[clap(long, arg_enum, default_value = "head")]
+    truncation: Truncation,
+    /// head tokens to keep of head-tail truncation, default to half of the length left for sentence after special tokens
+    #[clap(long)]
+    head_length: Option<usize>,
     /// stopwords file for build vocabulary, only effective when the with-vocab is not set
     #[clap(long, visible_alias="stopwords")]
     stopwords_file: Option<String>,
+    /// tokenizer splits each word into subwords, default to keep each word as a token
+    #[clap(long, arg_enum)]
+    tokenizer: Option<TokenizerType>,
+    /// pattern of tokens for regex tokenizer, default to words and single punctuations
+    #[clap(long)]
+    token_pattern: Option<Regex>,
+    /// tokenizer.json of HuggingFace tokenizers, overrides the tokenizer, vocabulary and special tokens
+    #[clap(long)]
+    tokenizer_file: Option<String>,
+    /// classification token starts each sentence of wordpiece tokenizer
+    #[clap(long, default_value = "[CLS]")]
+    cls_token: String,
+    /// separator token ends each sentence of wordpiece tokenizer
+    #[clap(long, default_value = "[SEP]")]
+    sep_token: String,
+    /// lowercase text of wordpiece tokenizer as uncased BERT models, false for cased ones
+    #[clap(long, default_value = "true", action = ArgAction::Set)]
+    lowercase: bool,
+    /// strip accents of text of wordpiece tokenizer, default to lowercase
+    #[clap(long, action = ArgAction::Set)]
+    strip_accents: Option<bool>,
+    /// merges file of bpe tokenizer, only effective when the with-vocab is set, default to merges.txt of dataset path
+    #[clap(long, visible_alias="merges")]
+    merges_file: Option<String>,
     // /// with en language
     // #[clap(long)]
     // with_lang_en: bool,
     /// separator between word and tag
     #[clap(long, short, visible_alias="delimiter", default_value = "\t")]
     separator: String,
-    #[clap(long, visible_alias = "UNK", default_value = "<UNK>")]
+    /// unknown special token of vocabulary, default to [UNK] for wordpiece tokenizer
+    #[clap(long, visible_aliases = &["UNK", "unk-token"], default_value = "<UNK>", default_value_if("tokenizer", Some("wordpiece"), Some("[UNK]")))]
     unknown: String,
-    /// padding special token of vocabulary
-    #[clap(long, visible_alias = "PAD", default_value = "<PAD>")]
+    /// padding special token of vocabulary, default to [PAD] for wordpiece tokenizer
+    #[clap(long, visible_aliases = &["PAD", "pad-token"], default_value = "<PAD>", default_value_if("tokenizer", Some("wordpiece"), Some("[PAD]")))]
     padding: String,
     /// padding tag
     #[clap(long, default_value = "None")]
     padding_tag: String,
+    /// integer type of tag columns, auto picks the narrowest type holds all tags of train dataset
+    #[clap(long, arg_enum, default_value = "auto")]
+    tag_type: IdType,
+    /// how the tag of word is aligned to its subwords
+    #[clap(long, arg_enum, default_value = "first")]
+    alignment: Alignment,
+    /// tag id of subwords left out of training, the following subwords of first alignment and the special tokens of wordpiece and tokenizer-file, default to the padding tag
+    #[clap(long, default_value = "0")]
+    ignore_index: usize,
+    /// format of record file
+    #[clap(long, arg_enum, default_value = "ipc")]
+    format: Format,
+    /// compression of record file
+    #[clap(long, arg_enum)]
+    compression: Option<Compression>,
+    /// max rows of row group, only effective for parquet format
+    #[clap(long)]
+    row_group_size: Option<usize>,
+    /// write records to stdout one split after another, only supported by ipc-stream format
+    #[clap(long)]
+    stdout: bool,
+    /// samples read, encoded and written at a time, peak memory grows with it
+    #[clap(long, default_value = "100000")]
+    chunk_size: usize,
+    /// policy for invalid samples, skip and log write them to <split>.rejected.txt of output path, log also reports each error
+    #[clap(long, arg_enum, default_value = "fail")]
+    on_error: OnError,
+    /// layout of sequence columns in record file
+    #[clap(long, arg_enum, default_value = "columns")]
+    layout: Layout,
+    /// write length column of sequence before padding
+    #[clap(long)]
+    with_length: bool,
+    /// write attention mask column of sequence
+    #[clap(long)]
+    with_attention_mask: bool,
+    /// write line_no column of the first line (starts from 1) of sentence of each record
+    #[clap(long)]
+    with_line_no: bool,
+    /// write word_ids column of the word (starts from 1) each subword belongs to, 0 for special tokens and padding
+    #[clap(long)]
+    with_word_ids: bool,
+    /// strategy for sentence longer than sequence-length
+    #[clap(long, arg_enum, default_value = "truncate")]
+    overflow: Overflow,
+    /// step between windows of split overflow, default to sequence-length
+    #[clap(long)]
+    stride: Option<usize>,
+}
+
+/// tags of the subwords of a word
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment{
+    /// the first subword has the tag of word, the following ones have the ignore-index
+    First,
+    /// each subword has the tag of word
+    Propagate,
+    /// each subword has the tag of word, but `B-` tags of the following subwords become `I-` tags
+    Bio,
 }
 
 pub(crate) struct TaggingSample{
     tokens: Vec<String>,
-    tags: Vec<String>
+    tags: Vec<String>,
+    line_no: usize,
 }
 
 impl TaggingSample {
-    pub(crate) fn new(tokens: Vec<String>, tags: Vec<String>) -> Self{
+    pub(crate) fn new(tokens: Vec<String>, tags: Vec<String>, line_no: usize) -> Self{
         Self{
             tokens,
-            tags
+            tags,
+            line_no,
         }
     }
 }
 
 pub(crate) struct TaggingRecord{
     token_ids: Vec<usize>,
-    tag_ids: Vec<usize>
+    tag_ids: Vec<usize>,
+    /// word of each subword, starts from 1
+    word_ids: Vec<usize>,
+    line_no: usize,
 }
 
 impl TaggingRecord {
-    pub(crate) fn new(mut token_ids: Vec<usize>, mut tag_ids: Vec<usize>, max_length: usize) -> Self{
-        if token_ids.len() > max_length{
-            panic!("max length is less then current length {} !", token_ids.len());
-        }else if token_ids.len() < max_length{
-            let length = token_ids.len();
-            token_ids.append(&mut vec![0usize; max_length - length]);
-            tag_ids.append(&mut vec![0usize; max_length - length]);
-        }
+    pub(crate) fn new(token_ids: Vec<usize>, tag_ids: Vec<usize>, word_ids: Vec<usize>, line_no: usize) -> Self{
         Self{
             token_ids,
-            tag_ids
+            tag_ids,
+            word_ids,
+            line_no,
         }
     }
+
+    /// split sentence into windows of max_length, each window starts stride after the previous one
+    pub(crate) fn split(self, max_length: usize, stride: usize) -> Vec<Self>{
+        windows(self.token_ids.len(), max_length, stride)
+            .into_iter()
+            .map(|range|Self::new(
+                self.token_ids[range.clone()].to_vec(),
+                self.tag_ids[range.clone()].to_vec(),
+                self.word_ids[range].to_vec(),
+                self.line_no,
+            ))
+            .collect()
+    }
+
+    fn truncate(&mut self, truncator: &Truncator, max_length: usize){
+        truncator.truncate(&mut self.token_ids, max_length);
+        truncator.truncate(&mut self.tag_ids, max_length);
+        truncator.truncate(&mut self.word_ids, max_length);
+    }
 }
 
 pub struct TaggingBuilder<'a>{
     args: & 'a TaggingArgs,
     vocab: HashMap<String, usize>,
     tags: HashMap<String, usize>,
-    stopwords: HashSet<String>,
+    token_type: IdType,
+    tag_type: IdType,
+    /// splits each word into subwords, each word is a token without tokenizer
+    tokenizer: Box<dyn Tokenizer>,
+    /// `[CLS]` and `[SEP]` of wordpiece tokenizer
+    framing: Option<Framing>,
+    /// merges of bpe tokenizer
+    merges: Merges,
+    /// id of padding token, 0 except tokenizer file
+    pad_id: usize,
+    /// vocab and merges files given by user, kept as they are when saving vocabulary
+    user_files: Vec<PathBuf>,
+    /// `I-` tag of each `B-` tag for bio alignment
+    inside_tags: HashMap<usize, usize>,
+    overflowed: AtomicUsize,
 }
 
 impl<'a> TaggingBuilder<'a> {
@@ -97,149 +240,321 @@ impl<'a> TaggingBuilder<'a> {
             args,
             vocab: HashMap::new(),
             tags: HashMap::new(),
-            stopwords: HashSet::new(),
+            token_type: args.token_type,
+            tag_type: args.tag_type,
+            tokenizer: Box::new(WordTokenizer),
+            framing: None,
+            merges: Vec::new(),
+            pad_id: 0,
+            user_files: Vec::new(),
+            inside_tags: HashMap::new(),
+            overflowed: AtomicUsize::new(0),
         }
     }
-    fn save_tags(&self){
-        let output_path = self.get_output_path();
-        let tag_file = File::create(output_path.join("tags.txt")).expect("create tags file failed");
-        let mut writer = BufWriter::new(tag_file);
-        for (tag, idx) in &self.tags{
-            writeln!(&mut writer, "{}:{}", idx, tag).expect("write vocab line failed");
+    fn tokenizer_options(&self) -> TokenizerOptions<'_>{
+        TokenizerOptions{
+            tokenizer: self.args.tokenizer,
+            token_pattern: self.args.token_pattern.as_ref(),
+            tokenizer_file: self.args.tokenizer_file.as_deref(),
+            cls_token: &self.args.cls_token,
+            sep_token: &self.args.sep_token,
+            lowercase: self.args.lowercase,
+            strip_accents: self.args.strip_accents,
+            path: self.get_input_path(),
+            with_vocab: self.args.with_vocab,
+            vocab_file: self.args.vocab_file.as_deref(),
+            merges_file: self.args.merges_file.as_deref(),
+            stopwords_file: self.args.stopwords_file.as_deref(),
+            padding: &self.args.padding,
+            unknown: &self.args.unknown,
+            limits: VocabLimits{
+                max_vocab_size: self.args.max_vocab_size,
+                min_freq: self.args.min_freq,
+                max_df: self.args.max_df,
+            },
+            token_type: self.args.token_type,
         }
     }
+    /// max subwords of sentence, framing tokens are added after truncation
+    fn max_length(&self) -> usize{
+        self.args.sequence_length.saturating_sub(self.framing.as_ref().map_or(0, Framing::length))
+    }
+    /// tags of train dataset, with the `I-` tag of each `B-` tag for bio alignment
+    fn collect_tags(&self) -> Result<BTreeSet<String>, DatasetError>{
+        let mut tags = BTreeSet::new();
+        for samples in self.read_chunks("train.txt", |_, _|Ok(()))?{
+            samples?
+                .iter()
+                .for_each(|sample|tags.extend(sample.tags.iter().cloned()));
+        }
+        if self.args.alignment == Alignment::Bio{
+            // following subwords of a `B-` word need its `I-` tag even if train dataset has none
+            let inside = tags
+                .iter()
+                .filter_map(|tag|tag.strip_prefix("B-"))
+                .map(|tag|format!("I-{}", tag))
+                .collect::<Vec<_>>();
+            tags.extend(inside);
+        }
+        Ok(tags)
+    }
+    /// token ids, tag ids and word ids of the subwords of sentence
+    fn align(&self, sample: TaggingSample, unk_id: Option<usize>) -> TaggingRecord{
+        let mut record = TaggingRecord::new(Vec::new(), Vec::new(), Vec::new(), sample.line_no);
+        for (i, (token, tag)) in sample.tokens.iter().zip(&sample.tags).enumerate(){
+            let tag_id = self.tags[tag];
+            let token_ids = self.tokenizer
+                .tokenize(token)
+                .iter()
+                .filter_map(|subword|self.vocab.get(subword.as_ref()).copied().or(unk_id))
+                .collect::<Vec<_>>();
+            for (k, token_id) in token_ids.into_iter().enumerate(){
+                let tag_id = match self.args.alignment{
+                    _ if k == 0 => tag_id,
+                    Alignment::First => self.args.ignore_index,
+                    Alignment::Propagate => tag_id,
+                    Alignment::Bio => self.inside_tags.get(&tag_id).copied().unwrap_or(tag_id),
+                };
+                record.token_ids.push(token_id);
+                record.tag_ids.push(tag_id);
+                record.word_ids.push(i + 1);
+            }
+        }
+        record
+    }
+    fn save_tags(&self) -> Result<(), DatasetError>{
+        let mut tags = self.tags.iter().collect::<Vec<_>>();
+        tags.sort_by_key(|(_, idx)|**idx);
+        save_lines(&self.get_output_path().join("tags.txt"), tags.into_iter().map(|(tag, idx)|format!("{}:{}", idx, tag)))
+    }
+}
+
+/// word and tag columns of columns layout are placed position by position
+fn interleave<T>(layout: Layout, words: Vec<T>, tags: Vec<T>) -> Vec<T>{
+    match layout{
+        Layout::Columns => words
+            .into_iter()
+            .zip(tags)
+            .flat_map(|(word, tag)|[word, tag])
+            .collect(),
+        _ => words.into_iter().chain(tags).collect(),
+    }
 }
 
 impl <'a> IDataset<TaggingSample, TaggingRecord> for TaggingBuilder<'a> {
-    fn init(&mut self, train_samples: & Vec<TaggingSample>){
-        if let Some(stopwords_file) = &self.args.stopwords_file{
-            println!("reader stopwords file from {}", stopwords_file);
-            let stopwords_reader = BufReader::new(File::open(stopwords_file).expect("open stopwords file failed"));
-            stopwords_reader
-                .lines()
-                .filter_map(Result::ok)
-                .for_each(|word|{
-                    self.stopwords.insert(word);
-                })
-        }
-        self.vocab.insert(self.args.padding.to_owned(), 0);
-        let mut vocab = HashSet::new();
-        let mut tags = HashSet::new();
-        train_samples
-            .iter()
-            .for_each(|sample|{
-                sample.tokens
+    fn init(&mut self) -> Result<(), DatasetError>{
+        let tags = self.collect_tags()?;
+        self.tags.insert(self.args.padding_tag.to_owned(), 0);
+        tags
+            .into_iter()
+            .filter(|tag|tag != &self.args.padding_tag)
+            .enumerate()
+            .for_each(|(i, tag)|{self.tags.insert(tag, i + 1);});
+        if self.args.alignment == Alignment::Bio{
+            self.inside_tags = self.tags
+                .iter()
+                .filter_map(|(tag, idx)|Some((*idx, self.tags[&format!("I-{}", tag.strip_prefix("B-")?)])))
+                .collect();
+        }
+        // ignore index is written as a tag id too
+        let tag_size = self.tags.len().max(self.args.ignore_index + 1);
+        self.tag_type = resolve_id_type(self.args.tag_type, tag_size, "tags", "tag", &self.get_input_path().join("train.txt"))?;
+        self.save_tags()?;
+        let tokenization = tokenization(&self.tokenizer_options(), |tokenizer, add|{
+            for samples in self.read_chunks("train.txt", |_, _|Ok(()))?{
+                samples?
                     .iter()
-                    .for_each(|token|{
-                        vocab.insert(token.to_string());
+                    .for_each(|sample|{
+                        let subwords = sample.tokens
+                            .iter()
+                            .flat_map(|token|tokenizer.tokenize(token))
+                            .collect::<Vec<_>>();
+                        add(&subwords);
                     });
-                sample.tags.iter().for_each(|tag|{
-                    tags.insert(tag.to_string());
-                })
-            });
-        vocab
-            .into_iter()
-            .filter(|token|!self.stopwords.contains(token))
+            }
+            Ok(())
+        })?;
+        self.tokenizer = tokenization.tokenizer;
+        self.vocab = tokenization.vocab;
+        self.framing = tokenization.framing;
+        self.merges = tokenization.merges;
+        self.pad_id = tokenization.pad_id;
+        self.user_files = tokenization.user_files;
+        self.token_type = tokenization.token_type;
+        check_sequence_length(self.framing.as_ref(), self.args.sequence_length)?;
+        check_stride(self.args.overflow, self.args.stride, self.max_length())?;
+        Ok(())
+    }
+
+    fn read_dataset(&self, file: &Path) -> Result<RawSamples<'_>, DatasetError>{
+        let mut lines = read_lines(file)?;
+        // sentences are separated by empty lines
+        Ok(Box::new(iter::from_fn(move ||{
+            let mut group = Vec::new();
+            let mut line_no = 0;
+            for line in lines.by_ref(){
+                let (i, line) = match line{
+                    Ok(line) => line,
+                    Err(error) => return Some(Err(error)),
+                };
+                if !line.is_empty(){
+                    if group.is_empty(){
+                        line_no = i;
+                    }
+                    group.push(line);
+                }else if !group.is_empty(){
+                    break;
+                }
+            }
+            if group.is_empty(){
+                None
+            }else {
+                Some(Ok((line_no, group.join("\n"))))
+            }
+        })))
+    }
+
+    fn parse_sample(&self, _index: usize, line_no: usize, raw: &str) -> Result<TaggingSample, InvalidSample>{
+        let (tokens, tags): (Vec<_>, Vec<_>) = raw
+            .split('\n')
             .enumerate()
-            .for_each(|(i, word)|{self.vocab.insert(word, i + 1);});
-        self.tags.insert(self.args.padding_tag.to_owned(), 0);
-        tags.into_iter().enumerate().for_each(|(i, tag)|{self.tags.insert(tag, i + 1);});
-        self.save_tags();
-        let len = self.vocab.len();
-        self.vocab.insert(self.args.unknown.to_owned(), len);
-    }
-
-    fn read_dataset(&self, file: & str) -> Vec<TaggingSample>{
-        let base_path = Path::new(&self.args.path);
-        let data_file = base_path.join(file);
-        let data_reader = BufReader::new(File::open(data_file).unwrap());
-        let lines = data_reader
-            .lines()
-            .filter_map(Result::ok)
-            .collect::<Vec<_>>();
-        lines
-            .split(|s|s.is_empty())
-            .map(|group|{
-                let (tokens, tags): (Vec<_>, Vec<_>) = group
-                    .into_iter()
-                    .map(|line|line
-                        .split_once(&self.args.separator)
-                        .map(|item|(item.0.to_string(), item.1.to_string())).unwrap()
-                    )
-                    .unzip();
-                TaggingSample::new(tokens, tags)
+            .map(|(offset, line)|{
+                let (token, tag) = line
+                    .split_once(&self.args.separator)
+                    .ok_or_else(||InvalidSample::new(line_no + offset, column_after(line), format!("separator {:?} is not found", self.args.separator)))?;
+                // tags are empty while they are collected from train dataset
+                if !self.tags.is_empty() && !self.tags.contains_key(tag){
+                    let column = column_after(&line[..line.len() - tag.len()]);
+                    return Err(InvalidSample::new(line_no + offset, column, format!("tag `{}` is not found in train dataset", tag)));
+                }
+                Ok((token.to_string(), tag.to_string()))
             })
-            .collect()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+        Ok(TaggingSample::new(tokens, tags, line_no))
     }
 
     fn build_dataset(&self, samples: Vec<TaggingSample>) -> Vec<TaggingRecord>{
-        let max_length = self.args.sequence_length;
-        let unk_id = self.vocab.get(&self.args.unknown).unwrap();
-        let pb = ProgressBar::new(samples.len() as u64);
-        let records = samples
+        let max_length = self.max_length();
+        let stride = window_stride(self.args.stride, max_length);
+        let truncator = Truncator::new(self.args.truncation, self.args.head_length);
+        // vocabulary of tokenizer file may have no unknown token, tokens out of it are dropped
+        let unk_id = self.vocab.get(&self.args.unknown).copied();
+        samples
             .into_par_iter()
-            .map(|sample|{
-                pb.inc(1);
-                let word_ids = sample.tokens
-                    .into_iter()
-                    .map(|token|self.vocab
-                        .get(&token)
-                        .map(|it|*it)
-                        .unwrap_or(*unk_id)
-                    ).collect::<Vec<_>>();
-                let tag_ids = sample.tags
-                    .into_iter()
-                    .map(|tag|self.tags
-                        .get(&tag)
-                        .map(|it| *it)
-                        .unwrap_or(0)
-                    ).collect::<Vec<_>>();
-                TaggingRecord::new(word_ids, tag_ids, max_length)
+            .flat_map_iter(|sample|{
+                let mut record = self.align(sample, unk_id);
+                if record.token_ids.len() <= max_length{
+                    return vec![record];
+                }
+                self.overflowed.fetch_add(1, Ordering::Relaxed);
+                match self.args.overflow{
+                    Overflow::Truncate => {
+                        record.truncate(&truncator, max_length);
+                        vec![record]
+                    },
+                    Overflow::Drop => vec![],
+                    Overflow::Split => record.split(max_length, stride),
+                }
+            })
+            .map(|mut record|{
+                if let Some(framing) = &self.framing{
+                    framing.frame(&mut record.token_ids);
+                    framing.frame_with(&mut record.tag_ids, self.args.ignore_index);
+                    framing.frame_with(&mut record.word_ids, 0);
+                }
+                record
             })
-            .collect();
-        pb.finish_with_message("done");
-        records
+            .collect()
     }
-    fn save_vocab(&self){
-        let output_path = self.get_output_path();
-        let vocab_file = File::create(output_path.join("vocab.txt")).expect("create vocab file failed");
-        let mut writer = BufWriter::new(vocab_file);
-        for (word, idx) in &self.vocab{
-            writeln!(&mut writer, "{}\t{}", idx, word).expect("write vocab line failed");
+    fn save_vocab(&self) -> Result<(), DatasetError>{
+        save_vocab(&self.vocab, &self.get_output_path().join("vocab.txt"), &self.user_files)?;
+        if !self.merges.is_empty(){
+            save_merges(&self.merges, &self.get_output_path().join("merges.txt"), &self.user_files)?;
         }
+        Ok(())
     }
 
-    fn save_dataset(&self, records: Vec<TaggingRecord>, record_file: & str){
-        let output_path = self.get_output_path();
+    fn schema(&self) -> SchemaRef{
         let max_length = self.args.sequence_length;
-        let mut fields = Vec::new();
-        for k in 0..max_length{
-            let field = Field::new(&format!("word_{}", k), DataType::UInt32, false);
-            fields.push(field);
-            let field = Field::new(&format!("tag_{}", k), DataType::UInt8, false);
-            fields.push(field);
-        }
-        let schema = Arc::new(Schema::new(fields));
-        let record_file = File::create(output_path.join(record_file)).expect(&format!("create record file {} failed", record_file));
-        let mut writer = FileWriter::try_new(record_file, &schema).expect("create file writer failed");
-        for chunk in records.chunks(100){
-            let mut values = Vec::new();
-            for i in 0..max_length{
-                let series = chunk
-                    .iter()
-                    .map(|record|record.token_ids[i] as u32)
-                    .collect::<Vec<u32>>();
-                values.push(Arc::new(UInt32Array::from(series)) as ArrayRef);
-                let series = chunk
-                    .iter()
-                    .map(|record|record.tag_ids[i] as u8)
-                    .collect::<Vec<u8>>();
-                values.push(Arc::new(UInt8Array::from(series)) as ArrayRef);
-            }
-            let batch = RecordBatch::try_new(schema.clone(), values).expect("build batch error");
-            writer.write(&batch).expect("write record error");
+        let layout = self.args.layout;
+        let word_fields = id_sequence_fields(self.token_type, layout, "word", "input_ids", max_length);
+        let tag_fields = id_sequence_fields(self.tag_type, layout, "tag", "tag_ids", max_length);
+        let mut fields = interleave(layout, word_fields, tag_fields);
+        if self.args.with_line_no{
+            fields.push(Field::new("line_no", DataType::UInt64, false));
+        }
+        if self.args.with_length{
+            fields.push(Field::new("length", DataType::UInt32, false));
+        }
+        if self.args.with_attention_mask{
+            fields.append(&mut sequence_fields::<UInt8Type>(layout, "mask", "attention_mask", max_length));
+        }
+        if self.args.with_word_ids{
+            fields.append(&mut sequence_fields::<UInt32Type>(layout, "word_id", "word_ids", max_length));
         }
-        writer.finish().expect("finish write records error");
+        let metadata = HashMap::from([("token_type".to_string(), self.token_type.name().to_string())]);
+        Arc::new(Schema::new(fields).with_metadata(metadata))
+    }
+
+    fn record_batch(&self, schema: SchemaRef, records: &[TaggingRecord]) -> RecordBatch{
+        let max_length = self.args.sequence_length;
+        let layout = self.args.layout;
+        let token_ids = records
+            .iter()
+            .map(|record|record.token_ids.as_slice())
+            .collect::<Vec<_>>();
+        let tag_ids = records
+            .iter()
+            .map(|record|record.tag_ids.as_slice())
+            .collect::<Vec<_>>();
+        let mut values = interleave(
+            layout,
+            id_sequence_arrays(self.token_type, layout, &token_ids, max_length, self.pad_id),
+            id_sequence_arrays(self.tag_type, layout, &tag_ids, max_length, 0)
+        );
+        if self.args.with_line_no{
+            let line_nos = records
+                .iter()
+                .map(|record|record.line_no as u64)
+                .collect::<Vec<u64>>();
+            values.push(Arc::new(UInt64Array::from(line_nos)) as ArrayRef);
+        }
+        if self.args.with_length{
+            values.push(length_array(&token_ids));
+        }
+        if self.args.with_attention_mask{
+            values.append(&mut mask_arrays(layout, &token_ids, max_length));
+        }
+        if self.args.with_word_ids{
+            let word_ids = records
+                .iter()
+                .map(|record|record.word_ids.as_slice())
+                .collect::<Vec<_>>();
+            values.append(&mut sequence_arrays::<UInt32Type>(layout, &word_ids, max_length, 0));
+        }
+        RecordBatch::try_new(schema, values).expect("build batch error")
+    }
+    fn writer_options(&self) -> WriterOptions{
+        WriterOptions{
+            format: self.args.format,
+            compression: self.args.compression,
+            row_group_size: self.args.row_group_size,
+            stdout: self.args.stdout,
+        }
+    }
+    fn chunk_size(&self) -> usize{
+        self.args.chunk_size
+    }
+    fn on_error(&self) -> OnError{
+        self.args.on_error
+    }
+    fn get_input_path(&self) -> &Path{
+        Path::new(&self.args.path)
+    }
+    fn sample_terminator(&self) -> &'static str{
+        "\n\n"
     }
     fn get_output_path(&self) -> &Path {
         match &self.args.output_path{
@@ -247,4 +562,10 @@ impl <'a> IDataset<TaggingSample, TaggingRecord> for TaggingBuilder<'a> {
             Some(output_path) => Path::new(output_path)
         }
     }
+    fn report(&self, split: & str){
+        let overflowed = self.overflowed.swap(0, Ordering::Relaxed);
+        if overflowed > 0{
+            eprintln!("{} {} sentences are longer than {}, overflow with {:?}", overflowed, split, self.args.sequence_length, self.args.overflow);
+        }
+    }
 }
\ No newline at end of file
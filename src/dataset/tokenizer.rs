@@ -0,0 +1,475 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::path::{Path, PathBuf};
+use clap::ArgEnum;
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+use crate::dataset::bpe::{load_merges, BpeTokenizer, BpeTrainer, Merges};
+use crate::dataset::error::DatasetError;
+use crate::dataset::hf_tokenizer::HfTokenizer;
+use crate::dataset::id_type::{resolve_id_type, IdType};
+use crate::dataset::vocab::{check_max_df, load_stopwords, load_vocab, vocab_size, VocabCounter, VocabLimits};
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+/// default pattern of regex tokenizer, words and single punctuations
+const DEFAULT_PATTERN: &str = r"\w+|[^\w\s]";
+/// words longer than it are unknown for wordpiece, the same as BERT
+const MAX_WORD_CHARS: usize = 100;
+
+/// tokenizer for text of dataset
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenizerType{
+    /// each char is a token
+    Char,
+    /// tokens are separated by runs of unicode whitespace
+    Whitespace,
+    /// tokens are the matches of token-pattern
+    Regex,
+    /// each CJK char is a token, latin words and digits are kept whole, punctuations are single tokens
+    Mixed,
+    /// WordPiece of BERT with the vocabulary of vocab-file, continuation pieces start with `##`
+    Wordpiece,
+    /// byte-pair-encoding learned on train dataset, words of token-pattern are merged from chars
+    Bpe,
+}
+
+/// split text into tokens, which are looked up in vocabulary
+pub(crate) trait Tokenizer: Send + Sync{
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>;
+}
+
+/// the whole text is a token, words of tagging dataset are kept whole without tokenizer
+pub(crate) struct WordTokenizer;
+
+impl Tokenizer for WordTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        vec![Cow::Borrowed(text)]
+    }
+}
+
+pub(crate) struct CharTokenizer;
+
+impl Tokenizer for CharTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        text.char_indices()
+            .map(|(i, ch)|Cow::Borrowed(&text[i..i + ch.len_utf8()]))
+            .collect()
+    }
+}
+
+pub(crate) struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        text.split_whitespace().map(Cow::Borrowed).collect()
+    }
+}
+
+pub(crate) struct RegexTokenizer{
+    pattern: Regex,
+}
+
+impl Tokenizer for RegexTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        self.pattern
+            .find_iter(text)
+            .map(|token|Cow::Borrowed(token.as_str()))
+            .collect()
+    }
+}
+
+pub(crate) struct MixedTokenizer;
+
+impl Tokenizer for MixedTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        let mut tokens = Vec::new();
+        // start of the latin word being read
+        let mut word = None;
+        for (i, ch) in text.char_indices(){
+            if ch.is_alphanumeric() && !is_cjk(ch){
+                word.get_or_insert(i);
+                continue;
+            }
+            if let Some(start) = word.take(){
+                tokens.push(Cow::Borrowed(&text[start..i]));
+            }
+            if !ch.is_whitespace(){
+                tokens.push(Cow::Borrowed(&text[i..i + ch.len_utf8()]));
+            }
+        }
+        if let Some(start) = word{
+            tokens.push(Cow::Borrowed(&text[start..]));
+        }
+        tokens
+    }
+}
+
+/// basic tokenizer of BERT, control chars are removed, text is lowercased and its accents are
+/// stripped as uncased BERT models, words are separated by whitespace, each punctuation and CJK char
+/// is a token
+pub(crate) struct BasicTokenizer{
+    lowercase: bool,
+    strip_accents: bool,
+}
+
+impl BasicTokenizer {
+    pub fn new(lowercase: bool, strip_accents: bool) -> Self{
+        Self{
+            lowercase,
+            strip_accents,
+        }
+    }
+}
+
+impl Tokenizer for BasicTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        let mut text = Cow::Borrowed(text);
+        if self.lowercase{
+            text = Cow::Owned(text.to_lowercase());
+        }
+        if self.strip_accents{
+            text = Cow::Owned(remove_accents(&text));
+        }
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        for ch in text.chars(){
+            if ch == '\0' || ch == '\u{FFFD}' || is_control(ch){
+                continue;
+            }
+            if is_whitespace(ch) || is_cjk(ch) || is_punctuation(ch){
+                if !word.is_empty(){
+                    tokens.push(Cow::Owned(std::mem::take(&mut word)));
+                }
+                if !is_whitespace(ch){
+                    tokens.push(Cow::Owned(ch.to_string()));
+                }
+            }else {
+                word.push(ch);
+            }
+        }
+        if !word.is_empty(){
+            tokens.push(Cow::Owned(word));
+        }
+        tokens
+    }
+}
+
+/// WordPiece of BERT, each word of basic tokenizer is split into the longest pieces of vocabulary
+/// from left to right, a word can not be split into pieces is the unknown token
+pub(crate) struct WordPieceTokenizer{
+    basic: BasicTokenizer,
+    pieces: HashSet<String>,
+    unknown: String,
+}
+
+impl WordPieceTokenizer {
+    pub fn new(basic: BasicTokenizer, vocab: &HashMap<String, usize>, unknown: &str) -> Self{
+        Self{
+            basic,
+            pieces: vocab.keys().cloned().collect(),
+            unknown: unknown.to_string(),
+        }
+    }
+
+    fn split_word(&self, word: &str) -> Vec<String>{
+        if word.chars().count() > MAX_WORD_CHARS{
+            return vec![self.unknown.clone()];
+        }
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while start < word.len(){
+            let prefix = if start == 0 {""} else {"##"};
+            let longest = word[start..]
+                .char_indices()
+                .map(|(i, ch)|start + i + ch.len_utf8())
+                .rev()
+                .map(|end|(end, format!("{}{}", prefix, &word[start..end])))
+                .find(|(_, piece)|self.pieces.contains(piece));
+            match longest{
+                Some((end, piece)) => {
+                    pieces.push(piece);
+                    start = end;
+                },
+                None => return vec![self.unknown.clone()],
+            }
+        }
+        pieces
+    }
+}
+
+impl Tokenizer for WordPieceTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<Cow<'t, str>>{
+        self.basic
+            .tokenize(text)
+            .iter()
+            .flat_map(|word|self.split_word(word))
+            .map(Cow::Owned)
+            .collect()
+    }
+}
+
+/// ids of special tokens framing a sentence, such as `[CLS] sentence [SEP]`
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Framing{
+    prefix: Vec<usize>,
+    suffix: Vec<usize>,
+}
+
+impl Framing {
+    pub fn new(prefix: Vec<usize>, suffix: Vec<usize>) -> Self{
+        Self{
+            prefix,
+            suffix,
+        }
+    }
+
+    /// `[CLS] sentence [SEP]` of wordpiece tokenizer
+    pub fn bert(vocab: &HashMap<String, usize>, cls_token: &str, sep_token: &str, vocab_file: &Path) -> Result<Self, DatasetError>{
+        let id = |token: &str|vocab
+            .get(token)
+            .copied()
+            .ok_or_else(||DatasetError::content(vocab_file, format!("special token `{}` is not found in vocab file", token)));
+        Ok(Self::new(vec![id(cls_token)?], vec![id(sep_token)?]))
+    }
+
+    /// tokens added to each sentence
+    pub fn length(&self) -> usize{
+        self.prefix.len() + self.suffix.len()
+    }
+
+    pub fn frame(&self, word_ids: &mut Vec<usize>){
+        word_ids.splice(0..0, self.prefix.iter().copied());
+        word_ids.extend_from_slice(&self.suffix);
+    }
+
+    /// frame a sequence aligned with tokens, such as tags, with fill at the special tokens
+    pub fn frame_with(&self, ids: &mut Vec<usize>, fill: usize){
+        ids.splice(0..0, iter::repeat_n(fill, self.prefix.len()));
+        ids.extend(iter::repeat_n(fill, self.suffix.len()));
+    }
+}
+
+/// check sequence length holds a token besides the special tokens framing each sentence
+pub(crate) fn check_sequence_length(framing: Option<&Framing>, sequence_length: usize) -> Result<(), DatasetError>{
+    let special_tokens = framing.map_or(0, Framing::length);
+    if sequence_length > special_tokens{
+        return Ok(());
+    }
+    let message = match special_tokens{
+        0 => format!("sequence-length must be greater than 0, but got {}", sequence_length),
+        _ => format!("sequence-length must be greater than the {} special tokens added to each sentence, but got {}", special_tokens, sequence_length),
+    };
+    Err(DatasetError::arg("sequence-length", message))
+}
+
+/// check pair length holds a token besides the special tokens framing both texts of pair
+pub(crate) fn check_pair_length(framing: Option<&Framing>, pair_length: Option<usize>) -> Result<(), DatasetError>{
+    let special_tokens = 2 * framing.map_or(0, Framing::length);
+    match pair_length{
+        Some(pair_length) if pair_length <= special_tokens => {
+            let message = match special_tokens{
+                0 => format!("pair-length must be greater than 0, but got {}", pair_length),
+                _ => format!("pair-length must be greater than the {} special tokens added to the pair, but got {}", special_tokens, pair_length),
+            };
+            Err(DatasetError::arg("pair-length", message))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// chars of NFD without the nonspacing marks, such as `é` to `e`
+pub(crate) fn remove_accents(text: &str) -> String{
+    text.nfd()
+        .filter(|ch|get_general_category(*ch) != GeneralCategory::NonspacingMark)
+        .collect()
+}
+
+/// control chars of BERT, tab and line breaks are whitespace
+pub(crate) fn is_control(ch: char) -> bool{
+    if matches!(ch, '\t' | '\n' | '\r'){
+        return false;
+    }
+    matches!(get_general_category(ch),
+        GeneralCategory::Control
+        | GeneralCategory::Format
+        | GeneralCategory::Surrogate
+        | GeneralCategory::PrivateUse
+        | GeneralCategory::Unassigned)
+}
+
+pub(crate) fn is_whitespace(ch: char) -> bool{
+    matches!(ch, ' ' | '\t' | '\n' | '\r') || get_general_category(ch) == GeneralCategory::SpaceSeparator
+}
+
+/// ascii symbols are punctuations for BERT too
+pub(crate) fn is_punctuation(ch: char) -> bool{
+    ch.is_ascii_punctuation() || matches!(get_general_category(ch),
+        GeneralCategory::ConnectorPunctuation
+        | GeneralCategory::DashPunctuation
+        | GeneralCategory::OpenPunctuation
+        | GeneralCategory::ClosePunctuation
+        | GeneralCategory::InitialPunctuation
+        | GeneralCategory::FinalPunctuation
+        | GeneralCategory::OtherPunctuation)
+}
+
+/// CJK unified ideographs and their extensions, the same ranges of BERT basic tokenizer
+pub(crate) fn is_cjk(ch: char) -> bool{
+    matches!(ch as u32,
+        0x4E00..=0x9FFF
+        | 0x3400..=0x4DBF
+        | 0x20000..=0x2A6DF
+        | 0x2A700..=0x2B73F
+        | 0x2B740..=0x2B81F
+        | 0x2B820..=0x2CEAF
+        | 0xF900..=0xFAFF
+        | 0x2F800..=0x2FA1F)
+}
+
+/// tokenizer of tokenizer type, pattern is only used by regex and bpe tokenizer. Wordpiece and bpe
+/// need the vocabulary, so they are their pre-tokenizers until built with vocabulary
+pub(crate) fn build_tokenizer(tokenizer_type: TokenizerType, pattern: Option<&Regex>) -> Box<dyn Tokenizer>{
+    match tokenizer_type{
+        TokenizerType::Char => Box::new(CharTokenizer),
+        TokenizerType::Whitespace => Box::new(WhitespaceTokenizer),
+        TokenizerType::Regex | TokenizerType::Bpe => Box::new(RegexTokenizer{
+            pattern: pattern
+                .cloned()
+                .unwrap_or_else(||Regex::new(DEFAULT_PATTERN).expect("default token pattern is valid")),
+        }),
+        TokenizerType::Mixed => Box::new(MixedTokenizer),
+        TokenizerType::Wordpiece => Box::new(BasicTokenizer::new(true, true)),
+    }
+}
+
+/// tokenizer type of args, with-lang-en is kept as the whitespace tokenizer
+pub(crate) fn tokenizer_type(tokenizer: TokenizerType, with_lang_en: bool) -> TokenizerType{
+    if with_lang_en{
+        TokenizerType::Whitespace
+    }else {
+        tokenizer
+    }
+}
+
+/// tokenizer and vocabulary options of builder args
+pub(crate) struct TokenizerOptions<'a>{
+    /// tokenizer of text, each text is a token without it
+    pub tokenizer: Option<TokenizerType>,
+    pub token_pattern: Option<&'a Regex>,
+    /// tokenizer.json overrides the other options
+    pub tokenizer_file: Option<&'a str>,
+    pub cls_token: &'a str,
+    pub sep_token: &'a str,
+    /// lowercase of wordpiece tokenizer
+    pub lowercase: bool,
+    /// strip accents of wordpiece tokenizer, default to lowercase
+    pub strip_accents: Option<bool>,
+    /// dataset path, where vocab.txt, merges.txt and train.txt are by default
+    pub path: &'a Path,
+    pub with_vocab: bool,
+    pub vocab_file: Option<&'a str>,
+    pub merges_file: Option<&'a str>,
+    pub stopwords_file: Option<&'a str>,
+    pub padding: &'a str,
+    pub unknown: &'a str,
+    pub limits: VocabLimits,
+    pub token_type: IdType,
+}
+
+/// tokenizer and vocabulary of builder
+pub(crate) struct Tokenization{
+    pub tokenizer: Box<dyn Tokenizer>,
+    pub vocab: HashMap<String, usize>,
+    /// special tokens of wordpiece tokenizer and tokenizer file
+    pub framing: Option<Framing>,
+    /// merges of bpe tokenizer, written as merges.txt with the vocabulary
+    pub merges: Merges,
+    /// id of padding token, sequences are padded with it
+    pub pad_id: usize,
+    pub token_type: IdType,
+    /// vocab and merges files given by user, never overwritten by the output
+    pub user_files: Vec<PathBuf>,
+}
+
+/// add the tokens of a document of train dataset
+pub(crate) type AddDocument<'f> = dyn FnMut(&[Cow<str>]) + 'f;
+
+/// tokenizer and vocabulary of options. The vocabulary is loaded from tokenizer file or vocab file,
+/// otherwise counted or learned by bpe from the documents of train dataset, which `documents` splits
+/// with the given tokenizer and adds one by one
+pub(crate) fn tokenization<F>(options: &TokenizerOptions, documents: F) -> Result<Tokenization, DatasetError>
+    where F: FnOnce(&dyn Tokenizer, &mut AddDocument) -> Result<(), DatasetError>{
+    if let Some(tokenizer_file) = options.tokenizer_file{
+        let tokenizer_file = Path::new(tokenizer_file);
+        let tokenizer = HfTokenizer::load(tokenizer_file)?;
+        let vocab = tokenizer.vocab().clone();
+        let pad_id = tokenizer.pad_id();
+        return Ok(Tokenization{
+            token_type: resolve_id_type(options.token_type, vocab_size(&vocab).max(pad_id + 1), "tokens", "token", tokenizer_file)?,
+            framing: tokenizer.framing(),
+            pad_id,
+            tokenizer: Box::new(tokenizer),
+            vocab,
+            merges: Vec::new(),
+            user_files: Vec::new(),
+        });
+    }
+    let (wordpiece, bpe) = (options.tokenizer == Some(TokenizerType::Wordpiece), options.tokenizer == Some(TokenizerType::Bpe));
+    let mut tokenizer = match options.tokenizer{
+        None => Box::new(WordTokenizer),
+        Some(tokenizer) => build_tokenizer(tokenizer, options.token_pattern),
+    };
+    let mut merges = Vec::new();
+    let mut user_files = Vec::new();
+    let (vocab, vocab_source) = if options.with_vocab || wordpiece{
+        let vocab_file = match options.vocab_file{
+            None => options.path.join("vocab.txt"),
+            Some(vocab_file) => PathBuf::from(vocab_file)
+        };
+        user_files.push(vocab_file.clone());
+        (load_vocab(&vocab_file, options.padding, options.unknown)?, vocab_file)
+    }else if bpe{
+        let mut trainer = BpeTrainer::default();
+        documents(tokenizer.as_ref(), &mut |tokens|trainer.add(tokens.iter().map(|token|token.as_ref())))?;
+        let vocab;
+        (vocab, merges) = trainer.train(options.padding, options.unknown, options.limits.max_vocab_size, options.limits.min_freq);
+        (vocab, options.path.join("train.txt"))
+    }else {
+        check_max_df(options.limits.max_df)?;
+        let stopwords = match options.stopwords_file{
+            None => HashSet::new(),
+            Some(stopwords_file) => load_stopwords(stopwords_file)?,
+        };
+        let mut counter = VocabCounter::default();
+        documents(tokenizer.as_ref(), &mut |tokens|counter.add(tokens.iter().map(|token|token.as_ref())))?;
+        (counter.build(&stopwords, options.padding, options.unknown, &options.limits), options.path.join("train.txt"))
+    };
+    let mut framing = None;
+    if wordpiece{
+        let basic = BasicTokenizer::new(options.lowercase, options.strip_accents.unwrap_or(options.lowercase));
+        tokenizer = Box::new(WordPieceTokenizer::new(basic, &vocab, options.unknown));
+        framing = Some(Framing::bert(&vocab, options.cls_token, options.sep_token, &vocab_source)?);
+    }
+    if bpe{
+        if options.with_vocab{
+            let merges_file = match options.merges_file{
+                None => options.path.join("merges.txt"),
+                Some(merges_file) => PathBuf::from(merges_file)
+            };
+            merges = load_merges(&merges_file)?;
+            user_files.push(merges_file);
+        }
+        tokenizer = Box::new(BpeTokenizer::new(tokenizer, &vocab, &merges));
+    }
+    Ok(Tokenization{
+        token_type: resolve_id_type(options.token_type, vocab_size(&vocab), "tokens", "token", &vocab_source)?,
+        tokenizer,
+        vocab,
+        framing,
+        merges,
+        // padding token of loaded and counted vocabulary has id 0
+        pad_id: 0,
+        user_files,
+    })
+}
@@ -1,27 +1,170 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::iter;
 use std::path::Path;
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use crate::dataset::error::{open_file, DatasetError, InvalidSample, OnError};
+use crate::dataset::writer::{RecordWriter, WriterOptions};
 
-pub trait IDataset<S, R>{
-    fn init(&mut self, samples: & Vec<S>);
-    fn read_dataset(&self, file: & str) -> Vec<S>;
+/// rows of each record batch in record file
+const BATCH_SIZE: usize = 100;
+
+/// raw samples of dataset file, paired with the line number (starts from 1) of its first line
+pub type RawSamples<'b> = Box<dyn Iterator<Item = Result<(usize, String), DatasetError>> + 'b>;
+
+/// parsed samples of dataset file chunk by chunk
+pub type Chunks<'b, S> = Box<dyn Iterator<Item = Result<Vec<S>, DatasetError>> + 'b>;
+
+/// lines of file, each line is a raw sample
+pub(crate) fn read_lines(file: &Path) -> Result<RawSamples<'static>, DatasetError>{
+    let reader = BufReader::new(open_file(file)?);
+    let file = file.to_path_buf();
+    Ok(Box::new(reader
+        .lines()
+        .enumerate()
+        .map(move |(i, line)|line
+            .map(|line|(i + 1, line))
+            .map_err(|source|DatasetError::io(&file, source)))))
+}
+
+/// chunk size must be greater than 0, or no sample is ever read
+fn check_chunk_size(chunk_size: usize) -> Result<usize, DatasetError>{
+    if chunk_size == 0{
+        return Err(DatasetError::arg("chunk-size", "chunk-size must be greater than 0".to_string()));
+    }
+    Ok(chunk_size)
+}
+
+/// dataset builder streams every split through read, encode and write chunk by chunk,
+/// so peak memory is bounded by the chunk size instead of the dataset size
+pub trait IDataset<S: Send, R: Send>: Sync{
+    /// load labels and vocabulary, vocabulary of train dataset is counted in a separate pass over `read_chunks`
+    fn init(&mut self) -> Result<(), DatasetError>;
+    /// raw samples of dataset file in file order, a line or a group of lines each sample
+    fn read_dataset(&self, file: &Path) -> Result<RawSamples<'_>, DatasetError>;
+    /// parse raw sample, index is the position of sample in dataset file
+    fn parse_sample(&self, index: usize, line_no: usize, raw: &str) -> Result<S, InvalidSample>;
     fn build_dataset(&self, samples: Vec<S>) -> Vec<R>;
-    fn save_vocab(&self);
-    fn save_dataset(&self, records: Vec<R>, record_file: & str);
+    fn save_vocab(&self) -> Result<(), DatasetError>;
+    fn schema(&self) -> SchemaRef;
+    fn record_batch(&self, schema: SchemaRef, records: &[R]) -> RecordBatch;
+    fn writer_options(&self) -> WriterOptions;
+    /// samples of each chunk
+    fn chunk_size(&self) -> usize;
+    fn on_error(&self) -> OnError;
+    fn get_input_path(&self) -> &Path;
     fn get_output_path(&self) -> &Path;
+    /// report statistics of split after all chunks are saved
+    fn report(&self, _split: & str){}
+    /// written after each raw sample in rejected file
+    fn sample_terminator(&self) -> &'static str{
+        "\n"
+    }
+
+    /// samples of dataset file parsed in parallel chunk by chunk, keeping the order of file,
+    /// invalid samples are passed to `reject` unless the on-error policy is fail
+    fn read_chunks<'b>(&'b self, file: & str, mut reject: impl FnMut(&DatasetError, &str) -> Result<(), DatasetError> + 'b)
+        -> Result<Chunks<'b, S>, DatasetError>{
+        let chunk_size = check_chunk_size(self.chunk_size())?;
+        let on_error = self.on_error();
+        let data_file = self.get_input_path().join(file);
+        let mut raws = self.read_dataset(&data_file)?.enumerate();
+        let mut next_chunk = move ||{
+            let chunk = raws
+                .by_ref()
+                .take(chunk_size)
+                .map(|(index, raw)|raw.map(|(line_no, raw)|(index, line_no, raw)))
+                .collect::<Result<Vec<_>, _>>()?;
+            if chunk.is_empty(){
+                return Ok(None);
+            }
+            let parsed = chunk
+                .par_iter()
+                .map(|(index, line_no, raw)|self.parse_sample(*index, *line_no, raw))
+                .collect::<Vec<_>>();
+            let mut samples = Vec::with_capacity(parsed.len());
+            for ((_, _, raw), sample) in chunk.iter().zip(parsed){
+                match sample{
+                    Ok(sample) => samples.push(sample),
+                    Err(sample) => {
+                        let error = DatasetError::Invalid{file: data_file.clone(), sample};
+                        if on_error == OnError::Fail{
+                            return Err(error);
+                        }
+                        reject(&error, raw)?;
+                    }
+                }
+            }
+            Ok(Some(samples))
+        };
+        let mut failed = false;
+        Ok(Box::new(iter::from_fn(move ||{
+            if failed{
+                return None;
+            }
+            let chunk = next_chunk().transpose();
+            failed = matches!(chunk, Some(Err(_)));
+            chunk
+        })))
+    }
+
+    fn save_dataset(&self, split: & str) -> Result<(), DatasetError>{
+        eprintln!("Processing {} data...", split);
+        let on_error = self.on_error();
+        let rejected_file = self.get_output_path().join(format!("{}.rejected.txt", split));
+        let mut rejected = match on_error{
+            OnError::Fail => None,
+            _ => Some(BufWriter::new(File::create(&rejected_file)
+                .map_err(|source|DatasetError::io(&rejected_file, source))?)),
+        };
+        let mut rejections = 0;
+        let terminator = self.sample_terminator();
+        let schema = self.schema();
+        let mut writer = RecordWriter::try_new(self.get_output_path(), split, schema.clone(), &self.writer_options())?;
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(ProgressStyle::default_spinner().template("{spinner} {pos} samples {msg}"));
+        let chunks = self.read_chunks(&format!("{}.txt", split), |error, raw|{
+            rejections += 1;
+            if on_error == OnError::Log{
+                eprintln!("{}", error);
+            }
+            if let Some(rejected) = rejected.as_mut(){
+                write!(rejected, "{}{}", raw, terminator).map_err(|source|DatasetError::io(&rejected_file, source))?;
+            }
+            Ok(())
+        })?;
+        for samples in chunks{
+            let samples = samples?;
+            pb.inc(samples.len() as u64);
+            let records = self.build_dataset(samples);
+            for chunk in records.chunks(BATCH_SIZE){
+                writer.write(&self.record_batch(schema.clone(), chunk))?;
+            }
+        }
+        pb.finish_with_message("done");
+        writer.finish()?;
+        if let Some(mut rejected) = rejected{
+            rejected.flush().map_err(|source|DatasetError::io(&rejected_file, source))?;
+        }
+        if rejections > 0{
+            eprintln!("{} {} samples are rejected, written to {}", rejections, split, rejected_file.display());
+        }
+        self.report(split);
+        Ok(())
+    }
 
-    fn build(&mut self) {
-        let train_samples = self.read_dataset("train.txt");
-        println!("Processing train data...");
-        self.init(&train_samples);
-        let train_records = self.build_dataset(train_samples);
-        let dev_samples = self.read_dataset("dev.txt");
-        println!("Processing dev data...");
-        let dev_records = self.build_dataset(dev_samples);
-        let test_samples = self.read_dataset( "test.txt");
-        println!("Processing test data...");
-        let test_records = self.build_dataset(test_samples);
-        self.save_vocab();
-        self.save_dataset(train_records,  "train.records.ipc");
-        self.save_dataset(dev_records, "dev.records.ipc");
-        self.save_dataset(test_records,  "test.records.ipc");
+    fn build(&mut self) -> Result<(), DatasetError>{
+        self.writer_options().check()?;
+        check_chunk_size(self.chunk_size())?;
+        eprintln!("Counting train data...");
+        self.init()?;
+        self.save_vocab()?;
+        self.save_dataset("train")?;
+        self.save_dataset("dev")?;
+        self.save_dataset("test")?;
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,60 @@
+use clap::ArgEnum;
+
+/// strategy of which part of sentence to keep when truncating
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation{
+    /// keep the head of sentence
+    Head,
+    /// keep the tail of sentence
+    Tail,
+    /// keep head-length tokens of the head and the rest of the tail
+    HeadTail,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Truncator{
+    strategy: Truncation,
+    head_length: Option<usize>,
+}
+
+impl Truncator {
+    pub fn new(strategy: Truncation, head_length: Option<usize>) -> Self{
+        Self{
+            strategy,
+            head_length,
+        }
+    }
+
+    /// keep at most max_length items of sequence, which is the length left after special tokens,
+    /// head-tail keeps head-length items of the head, default to half of max_length
+    pub fn truncate<T>(&self, items: &mut Vec<T>, max_length: usize){
+        let length = items.len();
+        if length <= max_length{
+            return;
+        }
+        match self.strategy{
+            Truncation::Head => items.truncate(max_length),
+            Truncation::Tail => {
+                items.drain(..length - max_length);
+            },
+            Truncation::HeadTail => {
+                let head = self.head_length.unwrap_or(max_length / 2).min(max_length);
+                items.drain(head..length - (max_length - head));
+            }
+        }
+    }
+
+    /// truncate the longer sequence of pair one token at a time until both fit the budget
+    pub fn truncate_pair<T>(&self, front: &mut Vec<T>, back: &mut Vec<T>, budget: usize){
+        let (mut front_length, mut back_length) = (front.len(), back.len());
+        while front_length + back_length > budget{
+            if front_length >= back_length{
+                front_length -= 1;
+            }else {
+                back_length -= 1;
+            }
+        }
+        self.truncate(front, front_length);
+        self.truncate(back, back_length);
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::dataset::error::{column_after, open_file, DatasetError};
+
+/// load user vocabulary from file, each line is `id\ttoken` (the format of `vocab.txt` written by
+/// fast-record) or a bare token whose id is the line number
+pub(crate) fn load_vocab(vocab_file: &Path, padding: &str, unknown: &str) -> Result<HashMap<String, usize>, DatasetError>{
+    eprintln!("reader vocab file from {}", vocab_file.display());
+    let vocab_reader = BufReader::new(open_file(vocab_file)?);
+    let mut vocab = HashMap::new();
+    let mut ids = HashMap::new();
+    let mut padding_line = 0;
+    for (i, line) in vocab_reader.lines().enumerate(){
+        let line = line.map_err(|source|DatasetError::io(vocab_file, source))?;
+        let (idx, token, column) = match line.split_once('\t'){
+            Some((id, token)) => match id.parse::<usize>(){
+                // token starts after the tab
+                Ok(idx) => (idx, token.to_string(), column_after(id) + 1),
+                Err(_) => (i, line.to_owned(), 1)
+            },
+            None => (i, line.to_owned(), 1)
+        };
+        if let Some(other) = ids.insert(idx, token.to_owned()){
+            let message = format!("vocab id {} is used by both `{}` and `{}`", idx, other, token);
+            return Err(DatasetError::invalid(vocab_file, i + 1, 1, message));
+        }
+        if vocab.insert(token.to_owned(), idx).is_some(){
+            let message = format!("vocab token `{}` is duplicated", token);
+            return Err(DatasetError::invalid(vocab_file, i + 1, column, message));
+        }
+        if token == padding{
+            padding_line = i + 1;
+        }
+    }
+    match vocab.get(padding){
+        Some(0) => (),
+        Some(idx) => {
+            let message = format!("padding token `{}` must have id 0, but got {}", padding, idx);
+            return Err(DatasetError::invalid(vocab_file, padding_line, 1, message));
+        },
+        None => return Err(DatasetError::content(vocab_file, format!("padding token `{}` is not found in vocab file", padding)))
+    }
+    if !vocab.contains_key(unknown){
+        return Err(DatasetError::content(vocab_file, format!("unknown token `{}` is not found in vocab file", unknown)));
+    }
+    Ok(vocab)
+}
+
+/// load stopwords file, one word each line
+pub(crate) fn load_stopwords(stopwords_file: &str) -> Result<HashSet<String>, DatasetError>{
+    eprintln!("reader stopwords file from {}", stopwords_file);
+    let stopwords_file = Path::new(stopwords_file);
+    let stopwords_reader = BufReader::new(open_file(stopwords_file)?);
+    stopwords_reader
+        .lines()
+        .map(|line|line.map_err(|source|DatasetError::io(stopwords_file, source)))
+        .collect()
+}
+
+/// limits of build vocabulary from train dataset
+pub(crate) struct VocabLimits{
+    /// max vocabulary size including padding and unknown token
+    pub max_vocab_size: usize,
+    /// tokens occur less than min_freq times are pruned
+    pub min_freq: usize,
+    /// tokens occur in more than max_df ratio of documents are pruned
+    pub max_df: f64,
+}
+
+/// max-df is a ratio of documents in (0, 1], NaN is rejected as well
+pub(crate) fn check_max_df(max_df: f64) -> Result<(), DatasetError>{
+    if max_df > 0.0 && max_df <= 1.0{
+        return Ok(());
+    }
+    Err(DatasetError::arg("max-df", format!("max-df must be in (0, 1], but got {}", max_df)))
+}
+
+/// frequency of token, `count` is the occurrences and `df` is the number of documents contain it
+#[derive(Default)]
+struct TokenFreq{
+    count: usize,
+    df: usize,
+}
+
+/// token counter of train dataset for build vocabulary
+#[derive(Default)]
+pub(crate) struct VocabCounter{
+    counts: HashMap<String, TokenFreq>,
+    documents: usize,
+}
+
+impl VocabCounter {
+    /// count tokens of one document
+    pub fn add<'t>(&mut self, tokens: impl Iterator<Item=&'t str>){
+        self.documents += 1;
+        let mut seen = HashSet::new();
+        tokens.for_each(|token|{
+            let freq = self.counts.entry(token.to_string()).or_default();
+            freq.count += 1;
+            if seen.insert(token){
+                freq.df += 1;
+            }
+        });
+    }
+
+    /// build vocabulary ranked by descending frequency, ties broken lexicographically,
+    /// padding token is always 0 and unknown token is the last id, pruned tokens map to unknown
+    pub fn build(self, stopwords: &HashSet<String>, padding: &str, unknown: &str, limits: &VocabLimits) -> HashMap<String, usize>{
+        let documents = self.documents;
+        let mut counts = self.counts
+            .into_iter()
+            .filter(|(token, _)|token != padding && token != unknown)
+            .collect::<Vec<_>>();
+        let total = counts.len();
+        counts = prune(counts, "stopwords", |(token, _)|!stopwords.contains(token));
+        counts = prune(counts, "min-freq", |(_, freq)|freq.count >= limits.min_freq);
+        counts = prune(counts, "max-df", |(_, freq)|freq.df as f64 <= limits.max_df * documents as f64);
+        counts.sort_by(|a, b|b.1.count.cmp(&a.1.count).then_with(||a.0.cmp(&b.0)));
+        let size = counts.len().min(limits.max_vocab_size.saturating_sub(2));
+        let dropped = counts.split_off(size);
+        report("max-vocab-size", &dropped);
+        eprintln!("vocabulary keeps {} of {} distinct tokens", counts.len(), total);
+        let mut vocab = HashMap::new();
+        vocab.insert(padding.to_owned(), 0);
+        counts
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, (token, _))|{vocab.insert(token, i + 1);});
+        let len = vocab.len();
+        vocab.insert(unknown.to_owned(), len);
+        vocab
+    }
+}
+
+fn prune(counts: Vec<(String, TokenFreq)>, filter: &str, keep: impl Fn(&(String, TokenFreq)) -> bool) -> Vec<(String, TokenFreq)>{
+    let (kept, removed): (Vec<_>, Vec<_>) = counts.into_iter().partition(keep);
+    report(filter, &removed);
+    kept
+}
+
+fn report(filter: &str, removed: &[(String, TokenFreq)]){
+    let occurrences = removed.iter().map(|(_, freq)|freq.count).sum::<usize>();
+    eprintln!("{} filter removes {} types and {} token occurrences", filter, removed.len(), occurrences);
+}
+
+/// size of id range of vocabulary, user vocabulary may skip ids
+pub(crate) fn vocab_size(vocab: &HashMap<String, usize>) -> usize{
+    vocab.values().max().map_or(0, |idx|idx + 1)
+}
+
+/// whether file is one of the user files, which are loaded from dataset path or given by args
+/// and must not be overwritten when the output path is the dataset path
+pub(crate) fn is_user_file(file: &Path, user_files: &[PathBuf]) -> bool{
+    match file.canonicalize(){
+        Ok(file) => user_files
+            .iter()
+            .any(|user_file|user_file.canonicalize().ok().as_ref() == Some(&file)),
+        Err(_) => false,
+    }
+}
+
+/// save vocabulary in id order, each line is `id\ttoken`, the user vocab file is kept as it is
+pub(crate) fn save_vocab(vocab: &HashMap<String, usize>, vocab_file: &Path, user_files: &[PathBuf]) -> Result<(), DatasetError>{
+    if is_user_file(vocab_file, user_files){
+        eprintln!("keep user vocab file {}", vocab_file.display());
+        return Ok(());
+    }
+    let mut words = vocab.iter().collect::<Vec<_>>();
+    words.sort_by_key(|(_, idx)|**idx);
+    save_lines(vocab_file, words.into_iter().map(|(word, idx)|format!("{}\t{}", idx, word)))
+}
+
+/// create file and write each line, error with the file path
+pub(crate) fn save_lines(file: &Path, lines: impl Iterator<Item=String>) -> Result<(), DatasetError>{
+    let mut writer = BufWriter::new(File::create(file).map_err(|source|DatasetError::io(file, source))?);
+    for line in lines{
+        writeln!(&mut writer, "{}", line).map_err(|source|DatasetError::io(file, source))?;
+    }
+    writer.flush().map_err(|source|DatasetError::io(file, source))
+}
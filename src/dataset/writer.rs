@@ -0,0 +1,158 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::CompressionType;
+use arrow::ipc::writer::{FileWriter, IpcWriteOptions, StreamWriter};
+use arrow::record_batch::RecordBatch;
+use clap::ArgEnum;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression as ParquetCompression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use crate::dataset::error::DatasetError;
+
+/// format of record file
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format{
+    /// arrow ipc file
+    Ipc,
+    /// arrow ipc stream
+    IpcStream,
+    /// parquet file
+    Parquet,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str{
+        match self{
+            Format::Ipc => "ipc",
+            Format::IpcStream => "arrows",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+/// compression of record file, ipc format supports lz4 and zstd buffer compression
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression{
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+/// options of record file
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WriterOptions{
+    pub format: Format,
+    pub compression: Option<Compression>,
+    /// max rows of parquet row group
+    pub row_group_size: Option<usize>,
+    /// write ipc stream to stdout instead of file
+    pub stdout: bool,
+}
+
+impl WriterOptions {
+    /// check options before any file is created
+    pub fn check(&self) -> Result<(), DatasetError>{
+        if self.stdout && self.format != Format::IpcStream{
+            return Err(DatasetError::arg("stdout", "only ipc-stream format can be written to stdout, add --format ipc-stream".to_string()));
+        }
+        if self.format != Format::Parquet && self.compression == Some(Compression::Snappy){
+            return Err(DatasetError::arg("compression", "snappy compression is only supported by parquet format, ipc formats support lz4 and zstd".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// writer of `<split>.records.<format>` under output path
+pub(crate) struct RecordWriter{
+    /// record file, or `<stdout>`
+    file: PathBuf,
+    sink: Sink,
+}
+
+enum Sink{
+    Ipc(FileWriter<File>),
+    Stream(StreamWriter<BufWriter<Box<dyn Write>>>),
+    Parquet(ArrowWriter<File>),
+}
+
+impl RecordWriter {
+    pub fn try_new(output_path: &Path, split: &str, schema: SchemaRef, options: &WriterOptions) -> Result<Self, DatasetError>{
+        if options.stdout{
+            let file = PathBuf::from("<stdout>");
+            let sink: Box<dyn Write> = Box::new(io::stdout());
+            let writer = StreamWriter::try_new_with_options(BufWriter::new(sink), &schema, ipc_write_options(options)?)
+                .map_err(|err|write_error(&file, err))?;
+            return Ok(Self{file, sink: Sink::Stream(writer)});
+        }
+        let file = output_path.join(format!("{}.records.{}", split, options.format.extension()));
+        let record_file = File::create(&file).map_err(|source|DatasetError::io(&file, source))?;
+        let sink = match options.format{
+            Format::Ipc => {
+                let writer = FileWriter::try_new_with_options(record_file, &schema, ipc_write_options(options)?)
+                    .map_err(|err|write_error(&file, err))?;
+                Sink::Ipc(writer)
+            },
+            Format::IpcStream => {
+                let sink: Box<dyn Write> = Box::new(record_file);
+                let writer = StreamWriter::try_new_with_options(BufWriter::new(sink), &schema, ipc_write_options(options)?)
+                    .map_err(|err|write_error(&file, err))?;
+                Sink::Stream(writer)
+            },
+            Format::Parquet => {
+                let compression = match options.compression{
+                    None => ParquetCompression::UNCOMPRESSED,
+                    Some(Compression::Snappy) => ParquetCompression::SNAPPY,
+                    Some(Compression::Lz4) => ParquetCompression::LZ4_RAW,
+                    Some(Compression::Zstd) => ParquetCompression::ZSTD(ZstdLevel::default()),
+                };
+                let mut properties = WriterProperties::builder().set_compression(compression);
+                if let Some(row_group_size) = options.row_group_size{
+                    properties = properties.set_max_row_group_size(row_group_size);
+                }
+                let writer = ArrowWriter::try_new(record_file, schema, Some(properties.build()))
+                    .map_err(|err|write_error(&file, err))?;
+                Sink::Parquet(writer)
+            }
+        };
+        Ok(Self{file, sink})
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<(), DatasetError>{
+        match &mut self.sink{
+            Sink::Ipc(writer) => writer.write(batch).map_err(|err|write_error(&self.file, err)),
+            Sink::Stream(writer) => writer.write(batch).map_err(|err|write_error(&self.file, err)),
+            Sink::Parquet(writer) => writer.write(batch).map_err(|err|write_error(&self.file, err)),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), DatasetError>{
+        match self.sink{
+            Sink::Ipc(mut writer) => writer.finish().map_err(|err|write_error(&self.file, err)),
+            Sink::Stream(mut writer) => {
+                writer.finish().map_err(|err|write_error(&self.file, err))?;
+                writer.get_mut().flush().map_err(|source|DatasetError::io(&self.file, source))
+            },
+            Sink::Parquet(writer) => writer.close().map(|_|()).map_err(|err|write_error(&self.file, err)),
+        }
+    }
+}
+
+/// arrow and parquet errors of writing record file, such as a full disk
+fn write_error(file: &Path, err: impl Into<Box<dyn Error + Send + Sync>>) -> DatasetError{
+    DatasetError::io(file, io::Error::other(err))
+}
+
+fn ipc_write_options(options: &WriterOptions) -> Result<IpcWriteOptions, DatasetError>{
+    let compression = match options.compression{
+        None => None,
+        Some(Compression::Lz4) => Some(CompressionType::LZ4_FRAME),
+        Some(Compression::Zstd) => Some(CompressionType::ZSTD),
+        Some(Compression::Snappy) => unreachable!("snappy compression of ipc format is rejected by WriterOptions::check"),
+    };
+    IpcWriteOptions::default()
+        .try_with_compression(compression)
+        .map_err(|err|DatasetError::arg("compression", err.to_string()))
+}
@@ -3,6 +3,7 @@ use clap::{Parser, Subcommand};
 use dataset::{ClassifierArgs, ClassifierBuilder, SimilarityArgs, TaggingArgs};
 use crate::dataset::{SimilarityBuilder, TaggingBuilder};
 use crate::dataset::IDataset;
+use std::process;
 
 /// Record builder for NLP task
 #[derive(Parser)]
@@ -29,26 +30,30 @@ enum Command{
 
 fn main() {
     let fast_record = FastRecord::parse();
-    match &fast_record.command.unwrap_or_else(||{
-        println!("fast-record must with subcommand, use `fast-record help` get the usage");
+    let built = match &fast_record.command.unwrap_or_else(||{
+        eprintln!("fast-record must with subcommand, use `fast-record help` get the usage");
         Command::Help
     }){
         Command::Classifier(args) => {
-            println!("classifier dataset args: {:?}", args);
+            eprintln!("classifier dataset args: {:?}", args);
             let mut builder = ClassifierBuilder::new(args);
-            builder.build();
+            builder.build()
         },
         Command::Similarity(args) => {
-            println!("similarity dataset args: {:?}", args);
+            eprintln!("similarity dataset args: {:?}", args);
             let mut builder = SimilarityBuilder::new(args);
-            builder.build();
+            builder.build()
         },
         Command::Tagging(args) => {
-            println!("tagging dataset args: {:?}", args);
+            eprintln!("tagging dataset args: {:?}", args);
             let mut builder = TaggingBuilder::new(args);
-            builder.build();
+            builder.build()
         },
-        Command::Help => ()
+        Command::Help => Ok(())
+    };
+    if let Err(err) = built{
+        eprintln!("error: {}", err);
+        process::exit(1);
     }
-    println!("finished record!");
+    eprintln!("finished record!");
 }
@@ -0,0 +1,154 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn split_overflow_emits_windows_with_doc_id() {
+    let dir = workspace("classifier_split_overflow");
+    write_classifier_splits(&dir);
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--sequence-length", "6", "--overflow", "split", "--stride", "3"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let column = |name: &str| column_u32(&batches, name);
+    let expected = SENTENCES
+        .iter()
+        .enumerate()
+        .flat_map(|(doc_id, sent)| {
+            let len = sent.chars().count();
+            let windows = if len <= 6 { 1 } else { (len - 6).div_ceil(3) + 1 };
+            (0..windows).map(move |window_index| (doc_id as u32, window_index as u32))
+        })
+        .collect::<Vec<_>>();
+    let (doc_ids, window_indexes): (Vec<_>, Vec<_>) = expected.into_iter().unzip();
+    assert_eq!(column("doc_id"), doc_ids);
+    assert_eq!(column("window_index"), window_indexes);
+}
+
+#[test]
+fn truncation_keeps_head_tail_or_both() {
+    let dir = workspace("classifier_truncation");
+    write_classifier_splits(&dir);
+    let sent = SENTENCES[0].chars().map(String::from).collect::<Vec<_>>();
+    let cases = [
+        ("head", vec!["今", "天", "天", "气"]),
+        ("tail", vec!["们", "去", "打", "球"]),
+        ("head-tail", vec!["今", "去", "打", "球"]),
+    ];
+    for (truncation, expected) in cases {
+        let output = dir.join(truncation);
+        run("classifier", &dir, &output, &["--sequence-length", "4", "--truncation", truncation, "--head-length", "1"]);
+        let vocab = read_vocab(&output);
+        let batches = read_records(&output.join("train.records.ipc"));
+        let word_ids = (0..4).map(|k| column_u32(&batches, &format!("word_{}", k))[0]).collect::<Vec<_>>();
+        let expected = expected.iter().map(|token| vocab[*token]).collect::<Vec<_>>();
+        assert_eq!(word_ids, expected, "truncation {} of {:?}", truncation, sent);
+    }
+}
+
+fn write_many_classes(dir: &std::path::Path, classes: usize) {
+    write_splits(dir, |i, sent| format!("{}\tc{}\n", sent, classes - 1 - i));
+    let class_file = (0..classes).map(|k| format!("c{}\n", k)).collect::<String>();
+    std::fs::write(dir.join("class.txt"), class_file).unwrap();
+}
+
+#[test]
+fn class_type_widens_for_large_class_set() {
+    let dir = workspace("classifier_class_type");
+    write_many_classes(&dir, 300);
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &[]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let class = batches[0].column(batches[0].schema().index_of("class").unwrap()).clone();
+    let class = class.as_any().downcast_ref::<arrow::array::UInt16Array>().unwrap();
+    assert_eq!(class.values().to_vec(), (0..SENTENCES.len()).map(|i| 299 - i as u16).collect::<Vec<_>>());
+}
+
+#[test]
+fn class_type_overflow_is_an_error() {
+    let dir = workspace("classifier_class_type_overflow");
+    write_many_classes(&dir, 300);
+    let output = run_output("classifier", &dir, &dir.join("output"), &["--class-type", "u8"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("300 classes overflow class type U8"));
+}
+
+fn write_multi_label_splits(dir: &std::path::Path) {
+    write_splits(dir, |i, sent| format!("{}\t{}\n", sent, ["a,c", "b", "c,a,c"][i % 3]));
+    std::fs::write(dir.join("class.txt"), "a\nb\nc\n").unwrap();
+}
+
+#[test]
+fn multi_label_writes_multi_hot_vector() {
+    let dir = workspace("classifier_multi_hot");
+    write_multi_label_splits(&dir);
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--multi-label", "multi-hot"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let hots = (0..3).map(|k| column_u8(&batches, &format!("class_{}", k))).collect::<Vec<_>>();
+    let expected = [[1, 0, 1], [0, 1, 0], [1, 0, 1]];
+    for (i, hot) in expected.iter().cycle().take(SENTENCES.len()).enumerate() {
+        assert_eq!(hots.iter().map(|column| column[i]).collect::<Vec<_>>(), hot.to_vec());
+    }
+}
+
+#[test]
+fn multi_label_writes_sorted_label_list() {
+    let dir = workspace("classifier_multi_label_list");
+    write_multi_label_splits(&dir);
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--multi-label", "list"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let classes = batches[0].column(batches[0].schema().index_of("classes").unwrap()).clone();
+    let classes = classes.as_any().downcast_ref::<arrow::array::ListArray>().unwrap();
+    let labels = (0..arrow::array::Array::len(classes))
+        .map(|i| {
+            let values = classes.value(i);
+            values.as_any().downcast_ref::<arrow::array::UInt8Array>().unwrap().values().to_vec()
+        })
+        .collect::<Vec<_>>();
+    let expected = [vec![0, 2], vec![1], vec![0, 2]];
+    assert_eq!(labels, expected.iter().cycle().take(SENTENCES.len()).cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn multi_label_reports_unknown_label_column() {
+    let dir = workspace("classifier_multi_label_unknown");
+    write_multi_label_splits(&dir);
+    std::fs::write(dir.join("dev.txt"), "今天\ta,x\n").unwrap();
+    let output = run_output("classifier", &dir, &dir.join("output"), &["--multi-label", "list"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("dev.txt:1:6: label `x` is not found in class.txt"));
+}
+
+#[test]
+fn classes_are_inferred_without_class_file() {
+    let dir = workspace("classifier_infer_classes");
+    write_splits(&dir, |i, sent| format!("{}\t{}\n", sent, ["b", "c", "c", "a", "c", "b"][i]));
+    for (order, expected) in [("sorted", "a\nb\nc\n"), ("frequency", "c\nb\na\n")] {
+        let output = dir.join(order);
+        run("classifier", &dir, &output, &["--class-order", order]);
+        assert_eq!(std::fs::read_to_string(output.join("class.txt")).unwrap(), expected);
+        let classes = expected.lines().collect::<Vec<_>>();
+        let batches = read_records(&output.join("train.records.ipc"));
+        let labels = column_u8(&batches, "class")
+            .into_iter()
+            .map(|class| classes[class as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["b", "c", "c", "a", "c", "b"]);
+    }
+}
+
+#[test]
+fn labels_absent_from_train_are_reported() {
+    let dir = workspace("classifier_infer_classes_unseen");
+    write_classifier_splits(&dir);
+    std::fs::remove_file(dir.join("class.txt")).unwrap();
+    std::fs::write(dir.join("dev.txt"), "今天\ta\n明天\td\n后天\te\n昨天\td\n").unwrap();
+    let output = run_output("classifier", &dir, &dir.join("output"), &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("dev.txt:2:4: label `d` is not found in train dataset"));
+
+    let output = run_output("classifier", &dir, &dir.join("output"), &["--on-error", "skip"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 labels of dev are absent from train dataset: d, e"));
+}
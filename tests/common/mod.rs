@@ -0,0 +1,137 @@
+#![allow(dead_code)]
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use arrow::array::{UInt8Array, UInt32Array, UInt64Array};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use arrow::ipc::reader::FileReader;
+use arrow::record_batch::RecordBatch;
+
+pub const SENTENCES: [&str; 6] = [
+    "今天天气很好我们去打球",
+    "股票市场上涨",
+    "手机电脑发布新产品",
+    "比赛下跌市场",
+    "我们发布新手机",
+    "今天股票上涨很好",
+];
+
+pub const RECORDS: [&str; 3] = ["train.records.ipc", "dev.records.ipc", "test.records.ipc"];
+
+pub fn workspace(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn write_splits(dir: &Path, line: impl Fn(usize, &str) -> String) {
+    for split in ["train.txt", "dev.txt", "test.txt"] {
+        let content = SENTENCES
+            .iter()
+            .enumerate()
+            .map(|(i, sent)| line(i, sent))
+            .collect::<String>();
+        fs::write(dir.join(split), content).unwrap();
+    }
+}
+
+pub fn write_classifier_splits(dir: &Path) {
+    write_splits(dir, |i, sent| format!("{}\t{}\n", sent, ["a", "b", "c"][i % 3]));
+    fs::write(dir.join("class.txt"), "a\nb\nc\n").unwrap();
+}
+
+pub fn write_similarity_splits(dir: &Path) {
+    write_splits(dir, |i, sent| format!("{}\t{}\t{}\n", sent, SENTENCES[(i + 1) % SENTENCES.len()], i % 2));
+}
+
+pub fn write_tagging_splits(dir: &Path) {
+    write_splits(dir, |i, sent| {
+        let tags = ["O", "B-LOC", "I-LOC", "B-PER"];
+        sent.chars()
+            .enumerate()
+            .map(|(k, ch)| format!("{}\t{}\n", ch, tags[(i + k) % tags.len()]))
+            .chain(std::iter::once("\n".to_string()))
+            .collect()
+    });
+}
+
+/// classifier splits of a single sentence `text` labeled `a`
+pub fn write_text_splits(dir: &Path, text: &str) {
+    for split in ["train.txt", "dev.txt", "test.txt"] {
+        fs::write(dir.join(split), format!("{}\ta\n", text)).unwrap();
+    }
+    fs::write(dir.join("class.txt"), "a\n").unwrap();
+}
+
+pub fn run_output(task: &str, input: &Path, output: &Path, extra: &[&str]) -> Output {
+    fs::create_dir_all(output).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_fast-record"))
+        .arg(task)
+        .arg("--path")
+        .arg(input)
+        .arg("--output-path")
+        .arg(output)
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+pub fn run(task: &str, input: &Path, output: &Path, extra: &[&str]) {
+    let output = run_output(task, input, output, extra);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+pub fn read_records(file: &Path) -> Vec<RecordBatch> {
+    let reader = FileReader::try_new(File::open(file).unwrap(), None).unwrap();
+    reader.map(Result::unwrap).collect()
+}
+
+pub fn num_rows(file: &Path) -> usize {
+    read_records(file).iter().map(RecordBatch::num_rows).sum()
+}
+
+pub fn read_vocab(output: &Path) -> std::collections::HashMap<String, u32> {
+    fs::read_to_string(output.join("vocab.txt"))
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (idx, token) = line.split_once('\t').unwrap();
+            (token.to_string(), idx.parse().unwrap())
+        })
+        .collect()
+}
+
+/// values of an unsigned integer column of any width
+pub fn column_u32(batches: &[RecordBatch], name: &str) -> Vec<u32> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let array = cast(batch.column(batch.schema().index_of(name).unwrap()), &DataType::UInt32).unwrap();
+            array.as_any().downcast_ref::<UInt32Array>().unwrap().values().to_vec()
+        })
+        .collect()
+}
+
+pub fn column_u8(batches: &[RecordBatch], name: &str) -> Vec<u8> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let array = batch.column(batch.schema().index_of(name).unwrap());
+            array.as_any().downcast_ref::<UInt8Array>().unwrap().values().to_vec()
+        })
+        .collect()
+}
+
+pub fn column_u64(batches: &[RecordBatch], name: &str) -> Vec<u64> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let array = batch.column(batch.schema().index_of(name).unwrap());
+            array.as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec()
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::*;
+
+fn assert_same_outputs(first: &Path, second: &Path, files: &[&str]) {
+    for file in files {
+        let a = fs::read(first.join(file)).unwrap();
+        let b = fs::read(second.join(file)).unwrap();
+        assert!(a == b, "{} differs between runs", file);
+    }
+}
+
+fn build_twice(task: &str, input: &Path, extra: &[&str], files: &[&str]) {
+    let first = input.join("first");
+    let second = input.join("second");
+    run(task, input, &first, extra);
+    run(task, input, &second, extra);
+    assert_same_outputs(&first, &second, files);
+}
+
+#[test]
+fn classifier_outputs_are_reproducible() {
+    let dir = workspace("deterministic_classifier");
+    write_classifier_splits(&dir);
+    build_twice("classifier", &dir, &["--max-vocab-size", "12"], &[&["vocab.txt"], &RECORDS[..]].concat());
+}
+
+#[test]
+fn similarity_outputs_are_reproducible() {
+    let dir = workspace("deterministic_similarity");
+    write_similarity_splits(&dir);
+    build_twice("similarity", &dir, &[], &[&["vocab.txt"], &RECORDS[..]].concat());
+}
+
+#[test]
+fn tagging_outputs_are_reproducible() {
+    let dir = workspace("deterministic_tagging");
+    write_tagging_splits(&dir);
+    build_twice("tagging", &dir, &[], &[&["vocab.txt", "tags.txt"], &RECORDS[..]].concat());
+}
+
+#[test]
+fn vocab_is_written_in_id_order() {
+    let dir = workspace("deterministic_vocab_order");
+    write_classifier_splits(&dir);
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &[]);
+    let vocab = fs::read_to_string(output.join("vocab.txt")).unwrap();
+    let ids = vocab
+        .lines()
+        .map(|line| line.split_once('\t').unwrap().0.parse::<usize>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, (0..ids.len()).collect::<Vec<_>>());
+}
+
+#[test]
+fn records_follow_input_lines() {
+    let dir = workspace("deterministic_line_no");
+    let lines = 5000;
+    for split in ["train.txt", "dev.txt", "test.txt"] {
+        let content = (0..lines)
+            .map(|i| format!("{}\t{}\n", SENTENCES[i % SENTENCES.len()], i % 3))
+            .collect::<String>();
+        fs::write(dir.join(split), content).unwrap();
+    }
+    fs::write(dir.join("class.txt"), "a\nb\nc\n").unwrap();
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--with-label-id", "--with-line-no", "--chunk-size", "1000"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let line_nos = column_u64(&batches, "line_no");
+    assert_eq!(line_nos, (1..=lines as u64).collect::<Vec<_>>());
+    let classes = column_u8(&batches, "class");
+    assert!(line_nos.iter().zip(classes).all(|(line_no, class)| (line_no - 1) % 3 == class as u64));
+}
+
+#[test]
+fn tagging_line_no_is_first_line_of_sentence() {
+    let dir = workspace("deterministic_tagging_line_no");
+    write_tagging_splits(&dir);
+    let output = dir.join("output");
+    run("tagging", &dir, &output, &["--with-line-no"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let expected = SENTENCES
+        .iter()
+        .scan(1, |line_no, sent| {
+            let first = *line_no;
+            *line_no += sent.chars().count() as u64 + 1;
+            Some(first)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(column_u64(&batches, "line_no"), expected);
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::*;
+
+fn break_line(file: &Path, line_no: usize, line: &str) {
+    let content = fs::read_to_string(file).unwrap();
+    let mut lines = content.lines().map(str::to_string).collect::<Vec<_>>();
+    lines[line_no - 1] = line.to_string();
+    fs::write(file, lines.join("\n") + "\n").unwrap();
+}
+
+#[test]
+fn fail_reports_file_line_and_column() {
+    let dir = workspace("errors_fail");
+    write_classifier_splits(&dir);
+    break_line(&dir.join("dev.txt"), 3, "手机电脑\td");
+    let output = run_output("classifier", &dir, &dir.join("output"), &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("dev.txt:3:6: label `d` is not found in class.txt"), "{}", stderr);
+}
+
+#[test]
+fn missing_class_file_with_label_id_is_an_error() {
+    let dir = workspace("errors_class_file");
+    write_classifier_splits(&dir);
+    fs::remove_file(dir.join("class.txt")).unwrap();
+    let output = run_output("classifier", &dir, &dir.join("output"), &["--with-label-id"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("class.txt"));
+}
+
+#[test]
+fn label_id_out_of_class_file_is_an_error() {
+    let dir = workspace("errors_label_id_range");
+    write_splits(&dir, |i, sent| format!("{}\t{}\n", sent, i % 3));
+    fs::write(dir.join("class.txt"), "a\nb\nc\n").unwrap();
+    run("classifier", &dir, &dir.join("valid"), &["--with-label-id"]);
+    break_line(&dir.join("test.txt"), 4, "比赛下跌市场\t3");
+    let output = run_output("classifier", &dir, &dir.join("output"), &["--with-label-id"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("test.txt:4:8: label id 3 is not found in class.txt"), "{}", stderr);
+}
+
+#[test]
+fn skip_writes_rejected_lines() {
+    let dir = workspace("errors_skip");
+    write_similarity_splits(&dir);
+    break_line(&dir.join("train.txt"), 2, "股票市场上涨");
+    break_line(&dir.join("train.txt"), 5, "我们发布新手机\t今天\tyes");
+    let output = dir.join("output");
+    let result = run_output("similarity", &dir, &output, &["--on-error", "skip"]);
+    assert!(result.status.success());
+    assert!(!String::from_utf8_lossy(&result.stderr).contains("train.txt:2:"));
+    assert_eq!(num_rows(&output.join("train.records.ipc")), SENTENCES.len() - 2);
+    assert_eq!(num_rows(&output.join("dev.records.ipc")), SENTENCES.len());
+    let rejected = fs::read_to_string(output.join("train.rejected.txt")).unwrap();
+    assert_eq!(rejected, "股票市场上涨\n我们发布新手机\t今天\tyes\n");
+    assert_eq!(fs::read_to_string(output.join("dev.rejected.txt")).unwrap(), "");
+}
+
+#[test]
+fn log_reports_each_rejected_sentence() {
+    let dir = workspace("errors_log");
+    write_tagging_splits(&dir);
+    let first = SENTENCES[0].chars().count();
+    break_line(&dir.join("test.txt"), first + 3, "市场");
+    let output = dir.join("output");
+    let result = run_output("tagging", &dir, &output, &["--on-error", "log"]);
+    assert!(result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains(&format!("test.txt:{}:3: separator \"\\t\" is not found", first + 3)), "{}", stderr);
+    assert_eq!(num_rows(&output.join("test.records.ipc")), SENTENCES.len() - 1);
+    let rejected = fs::read_to_string(output.join("test.rejected.txt")).unwrap();
+    let tags = ["O", "B-LOC", "I-LOC", "B-PER"];
+    let expected = SENTENCES[1]
+        .chars()
+        .enumerate()
+        .map(|(k, ch)| if k == 1 { "市场\n".to_string() } else { format!("{}\t{}\n", ch, tags[(1 + k) % tags.len()]) })
+        .collect::<String>();
+    assert_eq!(rejected, expected + "\n");
+}
+
+#[test]
+fn tags_absent_from_train_are_invalid_samples() {
+    let dir = workspace("errors_unseen_tag");
+    write_tagging_splits(&dir);
+    break_line(&dir.join("dev.txt"), 2, "天\tI-XYZ");
+    let result = run_output("tagging", &dir, &dir.join("fail"), &[]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("dev.txt:2:3: tag `I-XYZ` is not found in train dataset"), "{}", stderr);
+
+    let output = dir.join("skip");
+    run("tagging", &dir, &output, &["--on-error", "skip"]);
+    assert_eq!(num_rows(&output.join("dev.records.ipc")), SENTENCES.len() - 1);
+    assert_eq!(num_rows(&output.join("train.records.ipc")), SENTENCES.len());
+    let rejected = fs::read_to_string(output.join("dev.rejected.txt")).unwrap();
+    assert!(rejected.contains("天\tI-XYZ\n"), "{}", rejected);
+}
+
+/// stderr of a run rejected by its args, which writes no record file
+fn arg_error(task: &str, dir: &Path, name: &str, extra: &[&str]) -> String {
+    let output_path = dir.join(name);
+    let output = run_output(task, dir, &output_path, extra);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    let records = fs::read_dir(&output_path).unwrap().filter_map(Result::ok);
+    assert!(records.filter(|entry| entry.file_name().to_string_lossy().contains(".records.")).count() == 0);
+    stderr
+}
+
+#[test]
+fn stride_is_checked_only_for_split_overflow() {
+    let dir = workspace("errors_stride");
+    write_tagging_splits(&dir);
+    run("tagging", &dir, &dir.join("truncate"), &["--stride", "0", "--overflow", "truncate"]);
+    for stride in ["0", "33"] {
+        let stderr = arg_error("tagging", &dir, stride, &["--stride", stride, "--overflow", "split"]);
+        assert!(stderr.contains(&format!("--stride: stride must be in 1..=32, but got {}", stride)), "{}", stderr);
+    }
+    write_classifier_splits(&dir);
+    let stderr = arg_error("classifier", &dir, "classifier", &["--stride", "0", "--overflow", "split"]);
+    assert!(stderr.contains("--stride: stride must be in 1..=32, but got 0"), "{}", stderr);
+}
+
+#[test]
+fn snappy_compression_of_ipc_is_an_error() {
+    let dir = workspace("errors_compression");
+    write_classifier_splits(&dir);
+    for format in ["ipc", "ipc-stream"] {
+        let stderr = arg_error("classifier", &dir, format, &["--compression", "snappy", "--format", format]);
+        assert!(stderr.contains("--compression: snappy compression is only supported by parquet format"), "{}", stderr);
+    }
+}
+
+#[test]
+fn stdout_requires_ipc_stream_format() {
+    let dir = workspace("errors_stdout");
+    write_classifier_splits(&dir);
+    let stderr = arg_error("classifier", &dir, "output", &["--stdout"]);
+    assert!(stderr.contains("--stdout: only ipc-stream format can be written to stdout"), "{}", stderr);
+}
+
+#[test]
+fn zero_chunk_size_is_an_error() {
+    let dir = workspace("errors_chunk_size");
+    write_similarity_splits(&dir);
+    let stderr = arg_error("similarity", &dir, "output", &["--chunk-size", "0"]);
+    assert!(stderr.contains("--chunk-size: chunk-size must be greater than 0"), "{}", stderr);
+}
+
+#[test]
+fn unwritable_output_files_are_errors() {
+    let dir = workspace("errors_unwritable");
+    write_classifier_splits(&dir);
+    let output = dir.join("output");
+    fs::create_dir_all(output.join("vocab.txt")).unwrap();
+    let result = run_output("classifier", &dir, &output, &[]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    assert!(stderr.contains("vocab.txt"), "{}", stderr);
+
+    fs::remove_dir(output.join("vocab.txt")).unwrap();
+    fs::create_dir_all(output.join("train.records.ipc")).unwrap();
+    let result = run_output("classifier", &dir, &output, &[]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    assert!(stderr.contains("train.records.ipc"), "{}", stderr);
+}
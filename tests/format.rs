@@ -0,0 +1,126 @@
+mod common;
+
+use std::fs::{self, File};
+use std::io::Cursor;
+use std::process::Command;
+
+use arrow::ipc::reader::StreamReader;
+use arrow::record_batch::RecordBatch;
+use common::*;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::Compression;
+
+#[test]
+fn parquet_records_match_ipc_records() {
+    let dir = workspace("format_parquet");
+    write_tagging_splits(&dir);
+    let ipc = dir.join("ipc");
+    run("tagging", &dir, &ipc, &["--layout", "list"]);
+    let parquet = dir.join("parquet");
+    run("tagging", &dir, &parquet, &["--layout", "list", "--format", "parquet", "--compression", "zstd", "--row-group-size", "4"]);
+    for split in ["train", "dev", "test"] {
+        let expected = read_records(&ipc.join(format!("{}.records.ipc", split)));
+        let file = File::open(parquet.join(format!("{}.records.parquet", split))).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let metadata = builder.metadata().clone();
+        assert_eq!(metadata.num_row_groups(), SENTENCES.len().div_ceil(4));
+        assert!(matches!(metadata.row_group(0).column(0).compression(), Compression::ZSTD(_)));
+        assert_eq!(builder.schema().metadata(), expected[0].schema().metadata());
+        let batches = builder.build().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(batches[0].schema().fields(), expected[0].schema().fields());
+        let rows = |batches: &[RecordBatch]| arrow::compute::concat_batches(&batches[0].schema(), batches).unwrap();
+        assert_eq!(rows(&batches).columns(), rows(&expected).columns());
+    }
+}
+
+#[test]
+fn compressed_ipc_records_match_uncompressed_records() {
+    let dir = workspace("format_ipc_compression");
+    write_classifier_splits(&dir);
+    let plain = dir.join("plain");
+    run("classifier", &dir, &plain, &[]);
+    for compression in ["lz4", "zstd"] {
+        let output = dir.join(compression);
+        run("classifier", &dir, &output, &["--compression", compression]);
+        for record_file in RECORDS {
+            let rows = |batches: Vec<RecordBatch>| arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap();
+            let expected = rows(read_records(&plain.join(record_file)));
+            let actual = rows(read_records(&output.join(record_file)));
+            assert_eq!(actual, expected, "{} compression of {}", compression, record_file);
+        }
+    }
+}
+
+/// reads the first ipc file and checks every other ipc file holds the same table
+const PYARROW_SAME_TABLES: &str = r#"
+import sys
+import pyarrow as pa
+
+
+def read(path):
+    with pa.OSFile(path, 'rb') as source:
+        return pa.ipc.open_file(source).read_all()
+
+
+expected = read(sys.argv[1])
+for path in sys.argv[2:]:
+    assert read(path).equals(expected), path
+"#;
+
+/// needs python3 with pyarrow, run it with `cargo test --test format -- --ignored`
+#[test]
+#[ignore = "needs python3 with pyarrow"]
+fn compressed_ipc_records_are_readable_by_pyarrow() {
+    let dir = workspace("format_ipc_compression_pyarrow");
+    write_classifier_splits(&dir);
+    let plain = dir.join("plain");
+    run("classifier", &dir, &plain, &[]);
+    let mut files = vec![plain.join("train.records.ipc")];
+    for compression in ["lz4", "zstd"] {
+        let output = dir.join(compression);
+        run("classifier", &dir, &output, &["--compression", compression]);
+        files.push(output.join("train.records.ipc"));
+    }
+    let output = Command::new("python3")
+        .arg("-c")
+        .arg(PYARROW_SAME_TABLES)
+        .args(&files)
+        .output()
+        .expect("run python3");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn ipc_stream_records_are_written_to_files_or_stdout() {
+    let dir = workspace("format_ipc_stream");
+    write_similarity_splits(&dir);
+    let plain = dir.join("plain");
+    run("similarity", &dir, &plain, &[]);
+    let rows = |batches: Vec<RecordBatch>| arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap();
+
+    let stream = dir.join("stream");
+    run("similarity", &dir, &stream, &["--format", "ipc-stream", "--compression", "zstd"]);
+    for split in ["train", "dev", "test"] {
+        let file = File::open(stream.join(format!("{}.records.arrows", split))).unwrap();
+        let batches = StreamReader::try_new(file, None).unwrap().map(Result::unwrap).collect();
+        let expected = rows(read_records(&plain.join(format!("{}.records.ipc", split))));
+        assert_eq!(rows(batches), expected);
+    }
+
+    let stdout = dir.join("stdout");
+    fs::create_dir_all(&stdout).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_fast-record"))
+        .args(["similarity", "--format", "ipc-stream", "--stdout", "--path"])
+        .arg(&dir)
+        .arg("--output-path")
+        .arg(&stdout)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut cursor = Cursor::new(output.stdout);
+    for record_file in RECORDS {
+        let batches = StreamReader::try_new(&mut cursor, None).unwrap().map(Result::unwrap).collect();
+        assert_eq!(rows(batches), rows(read_records(&plain.join(record_file))));
+    }
+    assert_eq!(cursor.position() as usize, cursor.get_ref().len());
+}
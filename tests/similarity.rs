@@ -0,0 +1,137 @@
+mod common;
+
+use std::fs;
+
+use arrow::datatypes::DataType;
+use common::*;
+
+#[test]
+fn pair_length_truncates_longest_first() {
+    let dir = workspace("similarity_pair_length");
+    write_similarity_splits(&dir);
+    let output = dir.join("output");
+    run("similarity", &dir, &output, &["--sequence-length", "16", "--pair-length", "10"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let length = |prefix: &str, row: usize| (0..16)
+        .filter(|k| column_u32(&batches, &format!("{}_{}", prefix, k))[row] != 0)
+        .count();
+    for (row, sent) in SENTENCES.iter().enumerate() {
+        let front = sent.chars().count();
+        let back = SENTENCES[(row + 1) % SENTENCES.len()].chars().count();
+        let (a, b) = (length("text_a", row), length("text_b", row));
+        assert!(a + b <= 10);
+        assert_eq!(a + b, (front + back).min(10));
+        if front + back > 10 {
+            assert!(a.abs_diff(b) <= 1 || a == front || b == back);
+        }
+    }
+}
+
+#[test]
+fn length_and_attention_mask_follow_truncated_sequences() {
+    let dir = workspace("similarity_length_mask");
+    write_similarity_splits(&dir);
+    let output = dir.join("output");
+    run("similarity", &dir, &output, &["--sequence-length", "8", "--with-length", "--with-attention-mask"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let lengths = column_u32(&batches, "text_a_length");
+    let expected = SENTENCES.iter().map(|sent| sent.chars().count().min(8) as u32).collect::<Vec<_>>();
+    assert_eq!(lengths, expected);
+    for (row, length) in lengths.iter().enumerate() {
+        let mask = (0..8)
+            .map(|k| column_u8(&batches, &format!("text_a_mask_{}", k))[row] as u32)
+            .collect::<Vec<_>>();
+        let word_ids = (0..8)
+            .map(|k| column_u32(&batches, &format!("text_a_{}", k))[row])
+            .collect::<Vec<_>>();
+        assert_eq!(mask.iter().sum::<u32>(), *length);
+        assert!(mask.iter().zip(word_ids).all(|(mask, id)| (*mask == 1) == (id != 0)));
+    }
+}
+
+#[test]
+fn token_type_fits_vocabulary() {
+    let dir = workspace("similarity_token_type");
+    write_similarity_splits(&dir);
+    let output = dir.join("auto");
+    run("similarity", &dir, &output, &[]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let schema = batches[0].schema();
+    assert_eq!(schema.field_with_name("text_a_0").unwrap().data_type(), &DataType::UInt8);
+    assert_eq!(schema.metadata()["token_type"], "u8");
+
+    let output = dir.join("u32");
+    run("similarity", &dir, &output, &["--token-type", "u32"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    assert_eq!(batches[0].schema().field_with_name("text_b_0").unwrap().data_type(), &DataType::UInt32);
+    assert_eq!(batches[0].schema().metadata()["token_type"], "u32");
+}
+
+#[test]
+fn token_type_counts_sparse_vocabulary_ids() {
+    let dir = workspace("similarity_token_type_sparse");
+    write_similarity_splits(&dir);
+    fs::write(dir.join("vocab.txt"), "0\t<PAD>\n1\t今\n300\t<UNK>\n").unwrap();
+    let output = dir.join("auto");
+    run("similarity", &dir, &output, &["--with-vocab"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    assert_eq!(batches[0].schema().metadata()["token_type"], "u16");
+    assert_eq!(column_u32(&batches, "text_a_0")[0], 1);
+    assert_eq!(column_u32(&batches, "text_a_1")[0], 300);
+
+    let result = run_output("similarity", &dir, &dir.join("u8"), &["--with-vocab", "--token-type", "u8"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("301 tokens overflow token type U8"));
+}
+
+fn column_f32(batches: &[arrow::record_batch::RecordBatch], name: &str) -> Vec<f32> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let column = batch.column(batch.schema().index_of(name).unwrap()).clone();
+            column.as_any().downcast_ref::<arrow::array::Float32Array>().unwrap().values().to_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn float_label_writes_scores() {
+    let dir = workspace("similarity_float_label");
+    let scores = ["3.8", "0", "5.0", "1.5", "2.25", "4"];
+    write_splits(&dir, |i, sent| format!("{}\t{}\t{}\n", sent, SENTENCES[(i + 1) % SENTENCES.len()], scores[i]));
+    let output = dir.join("raw");
+    run("similarity", &dir, &output, &["--label-type", "float"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    assert_eq!(batches[0].schema().field_with_name("label").unwrap().data_type(), &DataType::Float32);
+    assert_eq!(column_f32(&batches, "label"), vec![3.8, 0.0, 5.0, 1.5, 2.25, 4.0]);
+
+    let output = dir.join("normalized");
+    run("similarity", &dir, &output, &["--label-type", "float", "--normalize-label"]);
+    let batches = read_records(&output.join("test.records.ipc"));
+    assert_eq!(column_f32(&batches, "label"), vec![0.76, 0.0, 1.0, 0.3, 0.45, 0.8]);
+}
+
+#[test]
+fn normalized_scores_out_of_train_range_are_clamped() {
+    let dir = workspace("similarity_float_label_clamped");
+    let scores = ["3.8", "0", "5.0", "1.5", "2.25", "4"];
+    write_splits(&dir, |i, sent| format!("{}\t{}\t{}\n", sent, SENTENCES[(i + 1) % SENTENCES.len()], scores[i]));
+    fs::write(dir.join("dev.txt"), "今天\t明天\t-1\n今天\t明天\t2.5\n今天\t明天\t6\n").unwrap();
+    let output = dir.join("output");
+    let result = run_output("similarity", &dir, &output, &["--label-type", "float", "--normalize-label"]);
+    assert!(result.status.success());
+    let batches = read_records(&output.join("dev.records.ipc"));
+    assert_eq!(column_f32(&batches, "label"), vec![0.0, 0.5, 1.0]);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("2 labels of dev are out of the range of train dataset, clamped to [0,1]"), "{}", stderr);
+}
+
+#[test]
+fn float_label_rejects_non_numeric_score() {
+    let dir = workspace("similarity_float_label_invalid");
+    write_similarity_splits(&dir);
+    fs::write(dir.join("dev.txt"), "今天\t明天\thigh\n").unwrap();
+    let output = run_output("similarity", &dir, &dir.join("output"), &["--label-type", "float"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("dev.txt:1:7: label `high` is not a finite float"));
+}
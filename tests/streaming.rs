@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use arrow::compute::concat_batches;
+use arrow::record_batch::RecordBatch;
+use common::*;
+
+fn read_rows(file: &Path) -> RecordBatch {
+    let batches = read_records(file);
+    concat_batches(&batches[0].schema(), &batches).unwrap()
+}
+
+fn assert_same_rows(task: &str, dir: &Path, extra: &[&str], files: &[&str]) {
+    let whole = dir.join("whole");
+    let chunked = dir.join("chunked");
+    run(task, dir, &whole, extra);
+    run(task, dir, &chunked, &[extra, &["--chunk-size", "4"]].concat());
+    for file in files {
+        assert_eq!(fs::read(whole.join(file)).unwrap(), fs::read(chunked.join(file)).unwrap(), "{} differs", file);
+    }
+    for file in RECORDS {
+        assert_eq!(read_rows(&whole.join(file)), read_rows(&chunked.join(file)), "{} differs", file);
+    }
+}
+
+#[test]
+fn chunked_classifier_keeps_rows_and_doc_id() {
+    let dir = workspace("streaming_classifier");
+    write_classifier_splits(&dir);
+    assert_same_rows("classifier", &dir, &["--sequence-length", "6", "--overflow", "split"], &["vocab.txt"]);
+}
+
+#[test]
+fn chunked_similarity_keeps_rows() {
+    let dir = workspace("streaming_similarity");
+    write_similarity_splits(&dir);
+    assert_same_rows("similarity", &dir, &[], &["vocab.txt"]);
+}
+
+#[test]
+fn chunked_tagging_keeps_rows() {
+    let dir = workspace("streaming_tagging");
+    write_tagging_splits(&dir);
+    assert_same_rows("tagging", &dir, &[], &["vocab.txt", "tags.txt"]);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use arrow::array::{Array, FixedSizeListArray, ListArray};
+use arrow::datatypes::DataType;
+use common::*;
+
+#[test]
+fn overflow_sentences_are_truncated_dropped_or_split() {
+    let dir = workspace("tagging_overflow");
+    write_tagging_splits(&dir);
+    let lengths = SENTENCES.iter().map(|sent| sent.chars().count()).collect::<Vec<_>>();
+    let max_length = 6;
+    let stride = 4;
+    let windows = |len: usize| if len <= max_length { 1 } else { (len - max_length).div_ceil(stride) + 1 };
+    let cases = [
+        ("truncate", SENTENCES.len()),
+        ("drop", lengths.iter().filter(|len| **len <= max_length).count()),
+        ("split", lengths.iter().map(|len| windows(*len)).sum()),
+    ];
+    for (overflow, expected) in cases {
+        let output = dir.join(overflow);
+        run("tagging", &dir, &output, &["--sequence-length", "6", "--overflow", overflow, "--stride", "4"]);
+        assert_eq!(num_rows(&output.join("train.records.ipc")), expected, "overflow {}", overflow);
+    }
+}
+
+#[test]
+fn list_layouts_store_sequences_in_one_column() {
+    let dir = workspace("tagging_layout");
+    write_tagging_splits(&dir);
+    let lengths = SENTENCES.iter().map(|sent| sent.chars().count()).collect::<Vec<_>>();
+
+    let output = dir.join("list");
+    run("tagging", &dir, &output, &["--layout", "list"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    assert_eq!(batches[0].num_columns(), 2);
+    for name in ["input_ids", "tag_ids"] {
+        let array = batches[0].column(batches[0].schema().index_of(name).unwrap());
+        let list = array.as_any().downcast_ref::<ListArray>().unwrap();
+        let list_lengths = (0..list.len()).map(|i| list.value_length(i) as usize).collect::<Vec<_>>();
+        assert_eq!(list_lengths, lengths);
+    }
+
+    let output = dir.join("fixed-list");
+    run("tagging", &dir, &output, &["--layout", "fixed-list", "--sequence-length", "16"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let array = batches[0].column(batches[0].schema().index_of("input_ids").unwrap());
+    let list = array.as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+    assert_eq!(list.value_length(), 16);
+    assert_eq!(list.len(), SENTENCES.len());
+}
+
+#[test]
+fn tag_type_sets_tag_column_width() {
+    let dir = workspace("tagging_tag_type");
+    write_tagging_splits(&dir);
+    let output = dir.join("output");
+    run("tagging", &dir, &output, &["--tag-type", "u32"]);
+    let batches = read_records(&output.join("train.records.ipc"));
+    let schema = batches[0].schema();
+    assert_eq!(schema.field_with_name("tag_0").unwrap().data_type(), &DataType::UInt32);
+    // `O` is the last of the sorted tags
+    assert_eq!(column_u32(&batches, "tag_0")[0], 4);
+}
+
+/// values of the first record of a list column
+fn first_list(output: &std::path::Path, name: &str) -> Vec<u64> {
+    let batches = read_records(&output.join("train.records.ipc"));
+    let array = batches[0].column(batches[0].schema().index_of(name).unwrap());
+    let values = array.as_any().downcast_ref::<ListArray>().unwrap().value(0);
+    let values = arrow::compute::cast(&values, &DataType::UInt64).unwrap();
+    values.as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap().values().to_vec()
+}
+
+#[test]
+fn subword_tags_follow_alignment() {
+    let dir = workspace("tagging_alignment");
+    for split in ["train.txt", "dev.txt", "test.txt"] {
+        std::fs::write(dir.join(split), "unaffable\tB-PER\nis\tO\n今天\tB-LOC\n\n").unwrap();
+    }
+    std::fs::write(dir.join("vocab.txt"), "[PAD]\n[UNK]\n[CLS]\n[SEP]\nun\n##aff\n##able\nis\n今\n天\n").unwrap();
+    let args = ["--tokenizer", "wordpiece", "--layout", "list", "--with-word-ids"];
+    // tags of first and propagate are None, B-LOC, B-PER, O, bio adds I-LOC and I-PER
+    let cases = [
+        ("first", &[][..], vec![0, 2, 0, 0, 3, 1, 0, 0]),
+        ("propagate", &[][..], vec![0, 2, 2, 2, 3, 1, 1, 0]),
+        ("bio", &[][..], vec![0, 2, 4, 4, 5, 1, 3, 0]),
+        ("ignore", &["--ignore-index", "255"][..], vec![255, 2, 255, 255, 3, 1, 255, 255]),
+    ];
+    for (name, extra, expected) in cases {
+        let output = dir.join(name);
+        let alignment = if name == "ignore" { "first" } else { name };
+        run("tagging", &dir, &output, &[&args[..], &["--alignment", alignment], extra].concat());
+        assert_eq!(first_list(&output, "input_ids"), [2, 4, 5, 6, 7, 8, 9, 3], "alignment {}", name);
+        assert_eq!(first_list(&output, "tag_ids"), expected, "alignment {}", name);
+        assert_eq!(first_list(&output, "word_ids"), [0, 1, 1, 1, 2, 3, 3, 0], "alignment {}", name);
+    }
+    let tags = std::fs::read_to_string(dir.join("bio").join("tags.txt")).unwrap();
+    assert!(tags.contains("4:I-PER"));
+}
@@ -0,0 +1,297 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::*;
+
+/// tokens of the only record of train dataset
+fn tokens(dir: &Path, name: &str, extra: &[&str]) -> Vec<String> {
+    let output = dir.join(name);
+    run("classifier", dir, &output, &[&["--layout", "list"], extra].concat());
+    let vocab = read_vocab(&output)
+        .into_iter()
+        .map(|(token, id)| (id, token))
+        .collect::<std::collections::HashMap<_, _>>();
+    let batches = read_records(&output.join("train.records.ipc"));
+    let input_ids = batches[0].column(batches[0].schema().index_of("input_ids").unwrap()).clone();
+    let input_ids = input_ids.as_any().downcast_ref::<arrow::array::ListArray>().unwrap().value(0);
+    let input_ids = arrow::compute::cast(&input_ids, &arrow::datatypes::DataType::UInt32).unwrap();
+    let input_ids = input_ids.as_any().downcast_ref::<arrow::array::UInt32Array>().unwrap();
+    input_ids.values().iter().map(|id| vocab[id].clone()).collect()
+}
+
+#[test]
+fn tokenizers_split_mixed_text() {
+    let dir = workspace("tokenizer_mixed_text");
+    write_text_splits(&dir, "我爱 Rust  语言,v2.0\u{3000}ok");
+    let cases = [
+        ("char", vec!["我", "爱", " ", "R", "u", "s", "t", " ", " ", "语", "言", ",", "v", "2", ".", "0", "\u{3000}", "o", "k"]),
+        ("whitespace", vec!["我爱", "Rust", "语言,v2.0", "ok"]),
+        ("regex", vec!["我爱", "Rust", "语言", ",", "v2", ".", "0", "ok"]),
+        ("mixed", vec!["我", "爱", "Rust", "语", "言", ",", "v2", ".", "0", "ok"]),
+    ];
+    for (tokenizer, expected) in cases {
+        assert_eq!(tokens(&dir, tokenizer, &["--tokenizer", tokenizer]), expected, "tokenizer {}", tokenizer);
+    }
+}
+
+#[test]
+fn regex_tokenizer_uses_token_pattern() {
+    let dir = workspace("tokenizer_pattern");
+    write_text_splits(&dir, "ab12cd3");
+    assert_eq!(tokens(&dir, "digits", &["--tokenizer", "regex", "--token-pattern", "[0-9]+"]), ["12", "3"]);
+    let output = run_output("classifier", &dir, &dir.join("invalid"), &["--tokenizer", "regex", "--token-pattern", "("]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn with_lang_en_is_whitespace_tokenizer() {
+    let dir = workspace("tokenizer_lang_en");
+    write_text_splits(&dir, "hello  big world");
+    assert_eq!(tokens(&dir, "lang_en", &["--with-lang-en"]), ["hello", "big", "world"]);
+}
+
+const BERT_VOCAB: &str = "[PAD]\n[UNK]\n[CLS]\n[SEP]\nun\n##aff\n##able\n今\n天\n!\n";
+const BERT_ARGS: [&str; 2] = ["--tokenizer", "wordpiece"];
+
+#[test]
+fn wordpiece_splits_longest_pieces_with_framing() {
+    let dir = workspace("tokenizer_wordpiece");
+    write_text_splits(&dir, "unaffable 今天! unx");
+    fs::write(dir.join("vocab.txt"), BERT_VOCAB).unwrap();
+    let expected = ["[CLS]", "un", "##aff", "##able", "今", "天", "!", "[UNK]", "[SEP]"];
+    assert_eq!(tokens(&dir, "wordpiece", &BERT_ARGS), expected);
+
+    let output = dir.join("truncated");
+    run("classifier", &dir, &output, &[&BERT_ARGS[..], &["--sequence-length", "5"]].concat());
+    let batches = read_records(&output.join("train.records.ipc"));
+    let word_ids = (0..5).map(|k| column_u32(&batches, &format!("word_{}", k))[0]).collect::<Vec<_>>();
+    assert_eq!(word_ids, [2, 4, 5, 6, 3]);
+}
+
+#[test]
+fn wordpiece_lowercases_and_strips_accents_by_default() {
+    let dir = workspace("tokenizer_wordpiece_lowercase");
+    write_text_splits(&dir, "Unaffable unäffable");
+    fs::write(dir.join("vocab.txt"), BERT_VOCAB).unwrap();
+    let expected = ["[CLS]", "un", "##aff", "##able", "un", "##aff", "##able", "[SEP]"];
+    assert_eq!(tokens(&dir, "uncased", &BERT_ARGS), expected);
+    let cased = [&BERT_ARGS[..], &["--lowercase", "false"]].concat();
+    assert_eq!(tokens(&dir, "cased", &cased), ["[CLS]", "[UNK]", "[UNK]", "[SEP]"]);
+    let accents = [&BERT_ARGS[..], &["--strip-accents", "false"]].concat();
+    assert_eq!(tokens(&dir, "accents", &accents), ["[CLS]", "un", "##aff", "##able", "[UNK]", "[SEP]"]);
+}
+
+#[test]
+fn wordpiece_frames_each_similarity_text() {
+    let dir = workspace("tokenizer_wordpiece_similarity");
+    for split in ["train.txt", "dev.txt", "test.txt"] {
+        fs::write(dir.join(split), "今天天天\tunaffable\t1\n").unwrap();
+    }
+    fs::write(dir.join("vocab.txt"), BERT_VOCAB).unwrap();
+    let output = dir.join("output");
+    run("similarity", &dir, &output, &[&BERT_ARGS[..], &["--sequence-length", "6"]].concat());
+    let batches = read_records(&output.join("train.records.ipc"));
+    let ids = |prefix: &str| (0..6).map(|k| column_u32(&batches, &format!("{}_{}", prefix, k))[0]).collect::<Vec<_>>();
+    assert_eq!(ids("text_a"), [2, 7, 8, 8, 8, 3]);
+    assert_eq!(ids("text_b"), [2, 4, 5, 6, 3, 0]);
+}
+
+#[test]
+fn wordpiece_keeps_bert_vocab_file() {
+    let dir = workspace("tokenizer_wordpiece_in_place");
+    write_text_splits(&dir, "unaffable 今天!");
+    fs::write(dir.join("vocab.txt"), BERT_VOCAB).unwrap();
+    // output path is the dataset path, where the BERT vocab.txt is loaded from
+    run("classifier", &dir, &dir, &["--tokenizer", "wordpiece"]);
+    assert_eq!(fs::read_to_string(dir.join("vocab.txt")).unwrap(), BERT_VOCAB);
+    assert_eq!(num_rows(&dir.join("train.records.ipc")), 1);
+}
+
+#[test]
+fn wordpiece_requires_special_tokens() {
+    let dir = workspace("tokenizer_wordpiece_special");
+    write_text_splits(&dir, "今天");
+    fs::write(dir.join("vocab.txt"), "[PAD]\n[UNK]\n今\n天\n").unwrap();
+    let output = run_output("classifier", &dir, &dir.join("output"), &BERT_ARGS);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("special token `[CLS]` is not found in vocab file"));
+}
+
+#[test]
+fn sequence_length_must_hold_more_than_special_tokens() {
+    let dir = workspace("tokenizer_wordpiece_sequence_length");
+    write_text_splits(&dir, "今天");
+    fs::write(dir.join("vocab.txt"), BERT_VOCAB).unwrap();
+    for sequence_length in ["2", "1"] {
+        let args = [&BERT_ARGS[..], &["--sequence-length", sequence_length, "--overflow", "split"]].concat();
+        let output = run_output("classifier", &dir, &dir.join("output"), &args);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("panicked"), "{}", stderr);
+        let message = format!("--sequence-length: sequence-length must be greater than the 2 special tokens added to each sentence, but got {}", sequence_length);
+        assert!(stderr.contains(&message), "{}", stderr);
+    }
+    run("classifier", &dir, &dir.join("output"), &[&BERT_ARGS[..], &["--sequence-length", "3"]].concat());
+}
+
+#[test]
+fn pair_length_must_hold_more_than_special_tokens() {
+    let dir = workspace("tokenizer_wordpiece_pair_length");
+    write_similarity_splits(&dir);
+    fs::write(dir.join("vocab.txt"), BERT_VOCAB).unwrap();
+    for pair_length in ["4", "0"] {
+        let args = [&BERT_ARGS[..], &["--pair-length", pair_length]].concat();
+        let output = run_output("similarity", &dir, &dir.join("output"), &args);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = format!("--pair-length: pair-length must be greater than the 4 special tokens added to the pair, but got {}", pair_length);
+        assert!(stderr.contains(&message), "{}", stderr);
+    }
+    run("similarity", &dir, &dir.join("output"), &[&BERT_ARGS[..], &["--pair-length", "5"]].concat());
+}
+
+#[test]
+fn bpe_learns_merges_of_train_dataset() {
+    let dir = workspace("tokenizer_bpe");
+    let train = ["low lower lowest", "low newer wider", "new newest low"];
+    fs::write(dir.join("train.txt"), train.iter().map(|sent| format!("{}\ta\n", sent)).collect::<String>()).unwrap();
+    fs::write(dir.join("dev.txt"), "lowest newer\ta\n").unwrap();
+    fs::write(dir.join("test.txt"), "xlow\ta\n").unwrap();
+    fs::write(dir.join("class.txt"), "a\n").unwrap();
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--tokenizer", "bpe", "--max-vocab-size", "16", "--layout", "list"]);
+    let merges = fs::read_to_string(output.join("merges.txt")).unwrap();
+    let merges = merges.lines().collect::<Vec<_>>();
+    assert_eq!(merges[..3], ["#version: 0.2", "l o", "lo w"]);
+    let vocab = read_vocab(&output);
+    assert_eq!(vocab.len(), 16);
+    assert_eq!(vocab["<PAD>"], 0);
+    assert_eq!(vocab["<UNK>"], 15);
+    assert!(vocab.contains_key("low"));
+
+    // records of the trained tokenizer and of the saved vocab and merges are the same
+    let reused = dir.join("reused");
+    let vocab_file = output.join("vocab.txt");
+    let merges_file = output.join("merges.txt");
+    run("classifier", &dir, &reused, &[
+        "--tokenizer", "bpe", "--layout", "list", "--with-vocab",
+        "--vocab-file", vocab_file.to_str().unwrap(), "--merges-file", merges_file.to_str().unwrap(),
+    ]);
+    for file in RECORDS {
+        assert_eq!(fs::read(output.join(file)).unwrap(), fs::read(reused.join(file)).unwrap(), "{}", file);
+    }
+    let batches = read_records(&output.join("test.records.ipc"));
+    let input_ids = batches[0].column(0).clone();
+    let input_ids = input_ids.as_any().downcast_ref::<arrow::array::ListArray>().unwrap().value(0);
+    let input_ids = arrow::compute::cast(&input_ids, &arrow::datatypes::DataType::UInt32).unwrap();
+    let input_ids = input_ids.as_any().downcast_ref::<arrow::array::UInt32Array>().unwrap();
+    // `x` is not a char of train dataset
+    assert_eq!(input_ids.values().to_vec(), [vocab["<UNK>"], vocab["low"]]);
+}
+
+#[test]
+fn bpe_merges_pairs_whose_count_went_down() {
+    let dir = workspace("tokenizer_bpe_decremented_pair");
+    // merging `a b` leaves `x a` of xab with a lower count, 3 of xa is still the most frequent pair
+    write_text_splits(&dir, "ab ab ab ab ab xab xa xa xa");
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--tokenizer", "bpe"]);
+    let merges = fs::read_to_string(output.join("merges.txt")).unwrap();
+    let merges = merges.lines().collect::<Vec<_>>();
+    assert_eq!(merges[1..3], ["a b", "x a"]);
+}
+
+const BERT_TOKENIZER: &str = r###"{
+  "version": "1.0",
+  "added_tokens": [
+    {"id": 0, "content": "[PAD]", "single_word": false, "lstrip": false, "rstrip": false, "normalized": false, "special": true},
+    {"id": 1, "content": "[UNK]", "single_word": false, "lstrip": false, "rstrip": false, "normalized": false, "special": true},
+    {"id": 2, "content": "[CLS]", "single_word": false, "lstrip": false, "rstrip": false, "normalized": false, "special": true},
+    {"id": 3, "content": "[SEP]", "single_word": false, "lstrip": false, "rstrip": false, "normalized": false, "special": true}
+  ],
+  "normalizer": {"type": "BertNormalizer", "clean_text": true, "handle_chinese_chars": true, "strip_accents": null, "lowercase": true},
+  "pre_tokenizer": {"type": "BertPreTokenizer"},
+  "post_processor": {
+    "type": "TemplateProcessing",
+    "single": [{"SpecialToken": {"id": "[CLS]", "type_id": 0}}, {"Sequence": {"id": "A", "type_id": 0}}, {"SpecialToken": {"id": "[SEP]", "type_id": 0}}],
+    "pair": [{"SpecialToken": {"id": "[CLS]", "type_id": 0}}, {"Sequence": {"id": "A", "type_id": 0}}, {"SpecialToken": {"id": "[SEP]", "type_id": 0}},
+             {"Sequence": {"id": "B", "type_id": 1}}, {"SpecialToken": {"id": "[SEP]", "type_id": 1}}],
+    "special_tokens": {
+      "[CLS]": {"id": "[CLS]", "ids": [2], "tokens": ["[CLS]"]},
+      "[SEP]": {"id": "[SEP]", "ids": [3], "tokens": ["[SEP]"]}
+    }
+  },
+  "decoder": {"type": "WordPiece", "prefix": "##", "cleanup": true},
+  "model": {
+    "type": "WordPiece", "unk_token": "[UNK]", "continuing_subword_prefix": "##", "max_input_chars_per_word": 100,
+    "vocab": {"[PAD]": 0, "[UNK]": 1, "[CLS]": 2, "[SEP]": 3, "hello": 4, "world": 5, "!": 6, "un": 7, "##aff": 8, "##able": 9, "今": 10, "天": 11, "cafe": 12}
+  }
+}"###;
+
+#[test]
+fn tokenizer_file_of_bert_wordpiece() {
+    let dir = workspace("tokenizer_file_bert");
+    write_text_splits(&dir, "Hello, WORLD! unaffable [SEP] 今天 Café xyz");
+    let tokenizer_file = dir.join("tokenizer.json");
+    fs::write(&tokenizer_file, BERT_TOKENIZER).unwrap();
+    let expected = ["[CLS]", "hello", "[UNK]", "world", "!", "un", "##aff", "##able", "[SEP]", "今", "天", "cafe", "[UNK]", "[SEP]"];
+    assert_eq!(tokens(&dir, "bert", &["--tokenizer-file", tokenizer_file.to_str().unwrap()]), expected);
+}
+
+const BYTE_LEVEL_TOKENIZER: &str = r###"{
+  "version": "1.0",
+  "added_tokens": [
+    {"id": 0, "content": "<s>", "single_word": false, "lstrip": false, "rstrip": false, "normalized": true, "special": true},
+    {"id": 1, "content": "<pad>", "single_word": false, "lstrip": false, "rstrip": false, "normalized": true, "special": true},
+    {"id": 2, "content": "</s>", "single_word": false, "lstrip": false, "rstrip": false, "normalized": true, "special": true}
+  ],
+  "normalizer": null,
+  "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": false, "trim_offsets": true, "use_regex": true},
+  "post_processor": {"type": "RobertaProcessing", "sep": ["</s>", 2], "cls": ["<s>", 0], "trim_offsets": true, "add_prefix_space": false},
+  "model": {
+    "type": "BPE", "dropout": null, "unk_token": null, "continuing_subword_prefix": "", "end_of_word_suffix": "", "fuse_unk": false,
+    "vocab": {"<s>": 0, "<pad>": 1, "</s>": 2, "h": 3, "e": 4, "l": 5, "o": 6, "w": 7, "r": 8, "d": 9, "Ġ": 10,
+              "ll": 11, "llo": 12, "he": 13, "hello": 14, "Ġw": 15, "or": 16, "Ġwor": 17},
+    "merges": ["l l", "ll o", "h e", "he llo", "Ġ w", ["o", "r"], "Ġw or"]
+  }
+}"###;
+
+#[test]
+fn tokenizer_file_of_byte_level_bpe() {
+    let dir = workspace("tokenizer_file_byte_level");
+    // the first of two spaces is a piece, the second one prefixes the next word
+    write_text_splits(&dir, "hello  world");
+    let tokenizer_file = dir.join("tokenizer.json");
+    fs::write(&tokenizer_file, BYTE_LEVEL_TOKENIZER).unwrap();
+    let expected = ["<s>", "hello", "Ġ", "Ġwor", "l", "d", "</s>"];
+    assert_eq!(tokens(&dir, "byte_level", &["--tokenizer-file", tokenizer_file.to_str().unwrap()]), expected);
+
+    let unsupported = BYTE_LEVEL_TOKENIZER.replace(r#""normalizer": null"#, r#""normalizer": {"type": "Precompiled", "precompiled_charsmap": ""}"#);
+    fs::write(&tokenizer_file, unsupported).unwrap();
+    let output = run_output("classifier", &dir, &dir.join("unsupported"), &["--tokenizer-file", tokenizer_file.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("normalizer `Precompiled` is not supported"));
+}
+
+#[test]
+fn tokenizer_file_pads_with_its_padding_token() {
+    let dir = workspace("tokenizer_file_padding");
+    write_text_splits(&dir, "hello");
+    let tokenizer_file = dir.join("tokenizer.json");
+    let word_ids = |name: &str| {
+        let output = dir.join(name);
+        let args = ["--tokenizer-file", tokenizer_file.to_str().unwrap(), "--token-type", "u32", "--sequence-length", "5"];
+        run("classifier", &dir, &output, &args);
+        let batches = read_records(&output.join("train.records.ipc"));
+        (0..5).map(|k| column_u32(&batches, &format!("word_{}", k))[0]).collect::<Vec<_>>()
+    };
+    // `<pad>` of added tokens without padding section
+    fs::write(&tokenizer_file, BYTE_LEVEL_TOKENIZER).unwrap();
+    assert_eq!(word_ids("added_token"), [0, 14, 2, 1, 1]);
+
+    let padding = r#""padding": {"strategy": "BatchLongest", "direction": "Right", "pad_to_multiple_of": null, "pad_id": 3, "pad_type_id": 0, "pad_token": "h"},"#;
+    fs::write(&tokenizer_file, BYTE_LEVEL_TOKENIZER.replacen("\"version\": \"1.0\",", &format!("\"version\": \"1.0\", {}", padding), 1)).unwrap();
+    assert_eq!(word_ids("padding"), [0, 14, 2, 3, 3]);
+}
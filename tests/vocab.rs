@@ -0,0 +1,134 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::*;
+
+/// token ids of the first record of train dataset
+fn word_ids(output: &Path, length: usize) -> Vec<u32> {
+    let batches = read_records(&output.join("train.records.ipc"));
+    (0..length).map(|k| column_u32(&batches, &format!("word_{}", k))[0]).collect()
+}
+
+/// stderr of a failed classifier run with vocab file
+fn vocab_error(name: &str, vocab: &str) -> String {
+    let dir = workspace(name);
+    write_text_splits(&dir, "今天");
+    fs::write(dir.join("vocab.txt"), vocab).unwrap();
+    let output = run_output("classifier", &dir, &dir.join("output"), &["--with-vocab"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    stderr
+}
+
+#[test]
+fn vocab_file_of_bare_tokens_uses_line_numbers() {
+    let dir = workspace("vocab_bare_tokens");
+    write_text_splits(&dir, "今天好");
+    fs::write(dir.join("vocab.txt"), "<PAD>\n<UNK>\n今\n天\n").unwrap();
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--with-vocab"]);
+    assert_eq!(word_ids(&output, 4), [2, 3, 1, 0]);
+}
+
+#[test]
+fn vocab_file_of_ids_and_tokens_keeps_ids() {
+    let dir = workspace("vocab_ids_tokens");
+    write_text_splits(&dir, "今天好");
+    fs::write(dir.join("vocab.txt"), "0\t<PAD>\n5\t今\n1\t<UNK>\n7\t天\n").unwrap();
+    let output = dir.join("output");
+    run("classifier", &dir, &output, &["--with-vocab"]);
+    assert_eq!(word_ids(&output, 4), [5, 7, 1, 0]);
+}
+
+#[test]
+fn user_vocab_file_is_not_overwritten() {
+    let dir = workspace("vocab_not_overwritten");
+    write_text_splits(&dir, "今天好");
+    let vocab = "<PAD>\n<UNK>\n今\n天\n";
+    fs::write(dir.join("vocab.txt"), vocab).unwrap();
+    // output path is the dataset path, where vocab.txt is loaded from
+    run("classifier", &dir, &dir, &["--with-vocab"]);
+    assert_eq!(fs::read_to_string(dir.join("vocab.txt")).unwrap(), vocab);
+    assert_eq!(word_ids(&dir, 4), [2, 3, 1, 0]);
+}
+
+#[test]
+fn padding_must_have_id_zero() {
+    let stderr = vocab_error("vocab_padding_id", "<UNK>\n<PAD>\n今\n");
+    assert!(stderr.contains("vocab.txt:2:1: padding token `<PAD>` must have id 0, but got 1"), "{}", stderr);
+}
+
+#[test]
+fn missing_special_tokens_are_errors() {
+    let stderr = vocab_error("vocab_missing_unknown", "<PAD>\n今\n天\n");
+    assert!(stderr.contains("vocab.txt: unknown token `<UNK>` is not found in vocab file"), "{}", stderr);
+    let stderr = vocab_error("vocab_missing_padding", "<UNK>\n今\n天\n");
+    assert!(stderr.contains("vocab.txt: padding token `<PAD>` is not found in vocab file"), "{}", stderr);
+}
+
+#[test]
+fn duplicated_ids_and_tokens_are_errors() {
+    let stderr = vocab_error("vocab_duplicated_id", "0\t<PAD>\n1\t<UNK>\n1\t今\n");
+    assert!(stderr.contains("vocab.txt:3:1: vocab id 1 is used by both `<UNK>` and `今`"), "{}", stderr);
+    let stderr = vocab_error("vocab_duplicated_token", "<PAD>\n<UNK>\n今\n今\n");
+    assert!(stderr.contains("vocab.txt:4:1: vocab token `今` is duplicated"), "{}", stderr);
+}
+
+/// vocabulary of output in id order
+fn vocab_tokens(output: &Path) -> Vec<String> {
+    let mut vocab = read_vocab(output).into_iter().collect::<Vec<_>>();
+    vocab.sort_by_key(|(_, id)| *id);
+    vocab.into_iter().map(|(token, _)| token).collect()
+}
+
+#[test]
+fn counted_vocab_ranks_tokens_by_frequency() {
+    let dir = workspace("vocab_ranking");
+    // c occurs 3 times, a and b twice, d once
+    write_text_splits(&dir, "cccbbaad");
+    let output = dir.join("ranked");
+    run("classifier", &dir, &output, &[]);
+    // ties of a and b are broken lexicographically, padding is 0 and unknown is the last id
+    assert_eq!(vocab_tokens(&output), ["<PAD>", "c", "a", "b", "d", "<UNK>"]);
+
+    // max vocab size counts padding and unknown token
+    let output = dir.join("limited");
+    run("classifier", &dir, &output, &["--max-vocab-size", "4"]);
+    assert_eq!(vocab_tokens(&output), ["<PAD>", "c", "a", "<UNK>"]);
+}
+
+#[test]
+fn pruned_tokens_map_to_unknown() {
+    let dir = workspace("vocab_pruning");
+    // counts are x 4, a 3, b 2, c 1 and d 1, x occurs in every sentence
+    fs::write(dir.join("train.txt"), "abx\ta\nacx\ta\nadx\ta\nbx\ta\n").unwrap();
+    for split in ["dev.txt", "test.txt"] {
+        fs::write(dir.join(split), "abcdx\ta\n").unwrap();
+    }
+    fs::write(dir.join("class.txt"), "a\n").unwrap();
+    let output = dir.join("output");
+    let result = run_output("classifier", &dir, &output, &["--min-freq", "2", "--max-df", "0.9"]);
+    assert!(result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("min-freq filter removes 2 types and 2 token occurrences"), "{}", stderr);
+    assert!(stderr.contains("max-df filter removes 1 types and 4 token occurrences"), "{}", stderr);
+    assert_eq!(vocab_tokens(&output), ["<PAD>", "a", "b", "<UNK>"]);
+    let batches = read_records(&output.join("test.records.ipc"));
+    let ids = (0..5).map(|k| column_u32(&batches, &format!("word_{}", k))[0]).collect::<Vec<_>>();
+    assert_eq!(ids, [1, 2, 3, 3, 3]);
+}
+
+#[test]
+fn max_df_out_of_range_is_an_error() {
+    let dir = workspace("vocab_max_df_range");
+    write_text_splits(&dir, "今天");
+    for max_df in ["0", "1.5", "NaN", "-0.5"] {
+        let result = run_output("classifier", &dir, &dir.join("output"), &[&format!("--max-df={}", max_df)]);
+        assert!(!result.status.success(), "max-df {}", max_df);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains(&format!("--max-df: max-df must be in (0, 1], but got {}", max_df)), "{}", stderr);
+    }
+}